@@ -33,6 +33,7 @@ import('monaco-editor-core').then(monaco => {
       //'resource',
       //'actor',
       'and',
+      'assert',
       'cut',
       'debug',
       'forall',
@@ -3,7 +3,19 @@
 use crate::PolarValue;
 use std::collections::HashMap;
 
-use crate::{Class, ClassBuilder};
+use crate::{Class, ClassBuilder, OsoError};
+
+/// Convert an already-rounded float to an integer, failing if it is NaN,
+/// infinite or out of range rather than saturating.
+fn float_to_integer(f: f64) -> Result<i64, OsoError> {
+    if f >= i64::MIN as f64 && f < i64::MAX as f64 {
+        Ok(f as i64)
+    } else {
+        Err(OsoError::Custom {
+            message: format!("{} cannot be converted to an Integer", f),
+        })
+    }
+}
 
 fn boolean() -> ClassBuilder<bool> {
     ClassBuilder::<bool>::with_default()
@@ -15,12 +27,25 @@ fn integer() -> ClassBuilder<i64> {
     ClassBuilder::<i64>::with_default()
         .with_equality_check()
         .name("Integer")
+        .add_method("abs", |i: &i64| {
+            i.checked_abs().ok_or_else(|| OsoError::Custom {
+                message: format!("abs of {} overflows an Integer", i),
+            })
+        })
+        .add_method("floor", |i: &i64| *i)
+        .add_method("ceil", |i: &i64| *i)
+        .add_method("round", |i: &i64| *i)
 }
 
 fn float() -> ClassBuilder<f64> {
     ClassBuilder::<f64>::with_default()
         .with_equality_check()
         .name("Float")
+        .add_method("abs", |f: &f64| f.abs())
+        // Rounding returns an integer; `round` rounds half away from zero.
+        .add_method("floor", |f: &f64| float_to_integer(f.floor()))
+        .add_method("ceil", |f: &f64| float_to_integer(f.ceil()))
+        .add_method("round", |f: &f64| float_to_integer(f.round()))
 }
 
 fn list() -> ClassBuilder<Vec<PolarValue>> {
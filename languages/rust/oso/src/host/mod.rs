@@ -30,6 +30,25 @@ fn metaclass() -> Class {
     Class::builder::<Class>().name("oso::host::Class").build()
 }
 
+/// A call from a policy into the application, passed to the observer set
+/// with [`Oso::set_host_call_observer`](crate::Oso::set_host_call_observer).
+#[derive(Clone, Debug)]
+pub struct HostCall {
+    /// The instance the attribute or method belongs to, or the new instance
+    /// for constructor calls. `None` for built-in values like strings.
+    pub instance_id: Option<u64>,
+    /// The attribute or method name, or the class name for constructor calls.
+    pub method: String,
+    /// The resolved arguments. `None` for attribute lookups.
+    pub args: Option<Vec<PolarValue>>,
+}
+
+pub type HostCallObserver = Arc<dyn Fn(&HostCall) + Send + Sync>;
+
+/// A binary operator registered with
+/// [`Oso::register_operator`](crate::Oso::register_operator).
+pub type HostOperator = Arc<dyn Fn(PolarValue, PolarValue) -> bool + Send + Sync>;
+
 /// Maintain mappings and caches for Rust classes & instances
 #[derive(Clone)]
 pub struct Host {
@@ -48,6 +67,12 @@ pub struct Host {
     class_names: HashMap<std::any::TypeId, String>,
 
     pub accept_expression: bool,
+
+    /// Called before each attribute lookup, method call and constructor call.
+    pub host_call_observer: Option<HostCallObserver>,
+
+    /// Map from operator names to their implementations.
+    pub operators: HashMap<String, HostOperator>,
 }
 
 impl Host {
@@ -57,6 +82,8 @@ impl Host {
             classes: HashMap::new(),
             instances: HashMap::new(),
             accept_expression: false,
+            host_call_observer: None,
+            operators: HashMap::new(),
             polar,
         };
         let type_class = metaclass();
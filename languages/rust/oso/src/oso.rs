@@ -1,4 +1,5 @@
 //! Communicate with the Polar virtual machine: load rules, make queries, etc/
+use polar_core::parser::parse_query;
 use polar_core::sources::Source;
 use polar_core::terms::{Call, Symbol, Term, Value};
 
@@ -9,7 +10,7 @@ use std::io::Read;
 use std::sync::Arc;
 
 use crate::host::Host;
-use crate::query::Query;
+use crate::query::{Query, ResultSet};
 use crate::{FromPolar, OsoError, PolarValue, ToPolar, ToPolarList};
 
 /// Oso is the main struct you interact with. It is an instance of the Oso authorization library
@@ -75,12 +76,9 @@ impl Oso {
         Action: ToPolar,
         Resource: ToPolar,
     {
-        let mut query = self.query_rule("allow", (actor, action, resource)).unwrap();
-        match query.next() {
-            Some(Ok(_)) => Ok(true),
-            Some(Err(e)) => Err(e),
-            None => Ok(false),
-        }
+        self.query_rule("allow", (actor, action, resource))
+            .unwrap()
+            .authorize()
     }
 
     /// Get the actions actor is allowed to take on resource.
@@ -214,8 +212,43 @@ impl Oso {
         Ok(query)
     }
 
+    /// Run `queries` in sequence, once per result of the previous query and
+    /// with that result's bindings already bound. Returns the results of the
+    /// last query, which include the bindings from every step. Every query is
+    /// parsed up front, so a syntax error in any step is reported before the
+    /// first one runs.
+    /// # Examples
+    /// ```ignore
+    /// oso.query_pipeline(&["user(name, u)", "allow(u, action, resource)"]);
+    /// ```
+    pub fn query_pipeline(&self, queries: &[&str]) -> crate::Result<Vec<ResultSet>> {
+        let mut results = vec![ResultSet::from_bindings(
+            Default::default(),
+            self.host.clone(),
+        )?];
+        let steps = queries
+            .iter()
+            .map(|src| parse_query(src))
+            .collect::<Result<Vec<Term>, _>>()?;
+        for step in steps {
+            let mut next = vec![];
+            for seed in results {
+                let query = self.inner.new_query_from_term(step.clone(), false);
+                check_messages!(self.inner);
+                for result in Query::seeded(query, seed)? {
+                    next.push(result?);
+                }
+            }
+            results = next;
+        }
+        Ok(results)
+    }
+
     /// Query the knowledge base but with a rule name and argument list.
     /// This allows you to pass in rust values.
+    ///
+    /// Pass `PolarValue::Variable` as an argument to get back the value
+    /// that argument was resolved to in each result.
     /// # Examples
     /// ```ignore
     /// oso.query_rule("is_admin", vec![User{name: "steve"}]);
@@ -240,6 +273,73 @@ impl Oso {
         Ok(query)
     }
 
+    /// Query the rule `name` once for each argument list in `arg_sets`,
+    /// returning the results for each in the same order. The queries are
+    /// prepared together, so this is cheaper than calling
+    /// [`Oso::query_rule`] in a loop, e.g. when checking access to many
+    /// resources at once.
+    /// # Examples
+    /// ```ignore
+    /// oso.query_rule_batch("allow", documents.iter().map(|d| (user.clone(), "read", d.clone())));
+    /// ```
+    pub fn query_rule_batch<A: ToPolarList>(
+        &self,
+        name: &str,
+        arg_sets: impl IntoIterator<Item = A>,
+    ) -> crate::Result<Vec<Vec<ResultSet>>> {
+        let mut query_host = self.host.clone();
+        let terms = arg_sets
+            .into_iter()
+            .map(|args| {
+                let args = args
+                    .to_polar_list()
+                    .iter()
+                    .map(|value| value.to_term(&mut query_host))
+                    .collect();
+                Term::new_from_ffi(Value::Call(Call {
+                    name: Symbol(name.to_string()),
+                    args,
+                    kwargs: None,
+                }))
+            })
+            .collect();
+        let queries = self.inner.new_queries_from_terms(terms, false);
+        check_messages!(self.inner);
+        queries
+            .into_iter()
+            .map(|query| Query::new(query, query_host.clone()).collect())
+            .collect()
+    }
+
+    /// Call `observer` before each attribute lookup, method call and
+    /// constructor call a policy makes into the application, e.g. to keep
+    /// an audit log of the data an authorization decision depended on.
+    pub fn set_host_call_observer<F>(&mut self, observer: F)
+    where
+        F: Fn(&crate::HostCall) + Send + Sync + 'static,
+    {
+        self.host.host_call_observer = Some(Arc::new(observer));
+    }
+
+    /// Register a binary operator implemented by `f`, e.g. to check whether
+    /// an IP address is in a CIDR block. Policies call it like a rule,
+    /// `in_cidr(ip, block)`, which succeeds if `f` returns `true`.
+    ///
+    /// Precedence:
+    /// - Built-in operators and keywords (`in`, `matches`, `print`, etc.)
+    ///   can't be registered, so an operator never replaces one.
+    /// - Rules defined in the policy take precedence over an operator with
+    ///   the same name.
+    /// - Both arguments must be bound when the operator is called.
+    pub fn register_operator<F>(&mut self, name: &str, f: F) -> crate::Result<()>
+    where
+        F: Fn(PolarValue, PolarValue) -> bool + Send + Sync + 'static,
+    {
+        self.inner.register_operator(Symbol::new(name))?;
+        self.host.operators.insert(name.to_owned(), Arc::new(f));
+        Ok(())
+    }
+
     /// Register a rust type as a Polar class.
     /// See [`oso::Class`] docs.
     pub fn register_class(&mut self, class: crate::host::Class) -> crate::Result<()> {
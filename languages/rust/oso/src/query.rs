@@ -8,6 +8,10 @@ use crate::{FromPolar, PolarValue};
 use polar_core::events::*;
 use polar_core::terms::*;
 
+/// Stands in for an instance whose constructor was not called in a dry run.
+#[derive(Debug)]
+struct DryRunInstance;
+
 impl Iterator for Query {
     type Item = crate::Result<ResultSet>;
     fn next(&mut self) -> Option<Self::Item> {
@@ -20,6 +24,11 @@ pub struct Query {
     /// Stores a map from call_id to the iterator the call iterates through
     iterators: HashMap<u64, PolarIterator>,
     host: Host,
+    /// Return host call errors instead of letting Polar prune the failing branch.
+    fail_fast_on_host_error: bool,
+    /// Record host calls instead of making them.
+    dry_run: bool,
+    planned_host_calls: Vec<crate::HostCall>,
 }
 
 impl Query {
@@ -28,7 +37,151 @@ impl Query {
             iterators: HashMap::new(),
             inner,
             host,
+            fail_fast_on_host_error: false,
+            dry_run: false,
+            planned_host_calls: vec![],
+        }
+    }
+
+    /// Create a query with every binding from `seed` already bound.
+    pub(crate) fn seeded(
+        mut inner: polar_core::query::Query,
+        seed: ResultSet,
+    ) -> crate::Result<Self> {
+        for (name, value) in seed.bindings {
+            inner.bind(name, value)?;
+        }
+        Ok(Self::new(inner, seed.host))
+    }
+
+    /// By default, some host call errors (e.g., looking up a missing attribute while
+    /// checking a `matches` pattern) only cause the current branch of the query to fail.
+    /// When `fail_fast` is set, any host call error aborts the query and is returned
+    /// from `next_result`.
+    pub fn set_fail_fast_on_host_error(&mut self, fail_fast: bool) {
+        self.fail_fast_on_host_error = fail_fast;
+    }
+
+    /// Fail the query with a `QueryTimeout` error once it has run for longer
+    /// than `timeout`, including time spent in host calls. Overrides the
+    /// `POLAR_TIMEOUT_MS` environment variable; `None` disables the timeout.
+    pub fn set_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        let timeout_ms = timeout.map_or(0, |t| t.as_millis().max(1) as u64);
+        self.inner.set_query_timeout_ms(timeout_ms);
+    }
+
+    /// In a dry run, attribute lookups, method calls and constructor calls
+    /// are recorded in [`Query::planned_host_calls`] instead of being made.
+    /// Each lookup or call returns a fresh unbound variable as a placeholder,
+    /// so the query can carry on past it. Looking up attributes on a
+    /// placeholder adds a constraint rather than a planned call, and calling
+    /// a method on one is an error. A constructor call binds an opaque
+    /// placeholder instance in its place: later goals can pass it around
+    /// and call methods on it (which are recorded too), but it matches no
+    /// registered class, so `matches` checks on it fail. Other host interactions (e.g., `matches` checks on real
+    /// instances) are still made as usual.
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    /// The host calls recorded so far in a dry run, in the order they
+    /// would have been made.
+    pub fn planned_host_calls(&self) -> &[crate::HostCall] {
+        &self.planned_host_calls
+    }
+
+    /// Yield each result as a delta: only the bindings whose values differ
+    /// from the previous result. The first result contains every binding.
+    pub fn deltas(self) -> impl Iterator<Item = crate::Result<ResultSet>> {
+        let mut previous = polar_core::kb::Bindings::new();
+        self.map(move |result| {
+            let result = result?;
+            let bindings = result
+                .bindings
+                .iter()
+                .filter(|(name, value)| previous.get(name) != Some(value))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+            previous = result.bindings;
+            Ok(ResultSet {
+                bindings,
+                host: result.host,
+            })
+        })
+    }
+
+    /// Whether the query has at least one result. Stops at the first result,
+    /// so the rest of the query is never run.
+    pub fn authorize(mut self) -> crate::Result<bool> {
+        self.next_result()
+            .transpose()
+            .map(|result| result.is_some())
+    }
+
+    /// Yield results in batches of up to `batch_size`. Each batch is only
+    /// computed when it is pulled. If the query fails partway through a batch,
+    /// the results gathered so far are yielded first and the error after them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batch_size` is 0.
+    pub fn run_batched(
+        mut self,
+        batch_size: usize,
+    ) -> impl Iterator<Item = crate::Result<Vec<ResultSet>>> {
+        assert!(batch_size > 0, "batch size must be non-zero");
+        let mut error = None;
+        std::iter::from_fn(move || {
+            if let Some(e) = error.take() {
+                return Some(Err(e));
+            }
+            let mut batch = Vec::with_capacity(batch_size);
+            while batch.len() < batch_size {
+                match self.next_result() {
+                    Some(Ok(result)) => batch.push(result),
+                    Some(Err(e)) if batch.is_empty() => return Some(Err(e)),
+                    Some(Err(e)) => {
+                        error = Some(e);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+            (!batch.is_empty()).then(|| Ok(batch))
+        })
+    }
+
+    /// Run the query to completion and group its results by the value bound
+    /// to `key`, converted to `K`. Results in which `key` is missing or left
+    /// unbound are grouped under `None`. Each group keeps its results in order.
+    pub fn run_grouped_by<K>(self, key: &str) -> crate::Result<HashMap<Option<K>, Vec<ResultSet>>>
+    where
+        K: FromPolar + Eq + std::hash::Hash,
+    {
+        let mut groups = HashMap::new();
+        for result in self {
+            let result = result?;
+            let group = match result.get(key) {
+                None | Some(PolarValue::Variable(_)) => None,
+                Some(value) => Some(K::from_polar(value)?),
+            };
+            groups.entry(group).or_insert_with(Vec::new).push(result);
+        }
+        Ok(groups)
+    }
+
+    /// Call `f` with each result in turn, e.g. to record each permission that
+    /// was granted. Stops at the first error, whether it comes from the query
+    /// or from `f`, and returns it; the rest of the query is never run.
+    pub fn try_for_each_result<F, E>(mut self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&ResultSet) -> Result<(), E>,
+        E: From<crate::OsoError>,
+    {
+        while let Some(result) = self.next_result() {
+            f(&result?)?;
         }
+        Ok(())
     }
 
     pub fn source(&self) -> String {
@@ -69,6 +222,11 @@ impl Query {
                     operator,
                     args,
                 } => self.handle_external_op(call_id, operator, args),
+                QueryEvent::ExternalOperator {
+                    call_id,
+                    name,
+                    args,
+                } => self.handle_external_operator(call_id, name, args),
                 QueryEvent::ExternalIsa {
                     call_id,
                     instance,
@@ -96,7 +254,9 @@ impl Query {
 
             match result {
                 // Only call errors get passed back.
-                Err(call_error @ OsoError::InvalidCallError { .. }) => {
+                Err(call_error @ OsoError::InvalidCallError { .. })
+                    if !self.fail_fast_on_host_error =>
+                {
                     tracing::error!("application invalid call error {}", call_error);
                     if let Err(e) = self.application_error(call_error) {
                         return Some(Err(e));
@@ -144,6 +304,13 @@ impl Query {
                         .iter()
                         .map(|term| PolarValue::from_term(term, &self.host))
                         .collect::<crate::Result<Vec<PolarValue>>>()?;
+                    if self.dry_run {
+                        self.plan_host_call(Some(instance_id), &name.0, Some(args));
+                        let placeholder = Instance::new(DryRunInstance);
+                        self.host.cache_instance(placeholder, Some(instance_id));
+                        return Ok(());
+                    }
+                    self.observe_host_call(Some(instance_id), &name.0, Some(&args));
                     self.host.make_instance(&name.0, args, instance_id)
                 }
             }
@@ -166,10 +333,16 @@ impl Query {
         match self.next_call_result(call_id) {
             Some(Ok(result)) => self.call_result(call_id, result),
             Some(Err(e)) => {
+                self.iterators.remove(&call_id);
                 self.call_result_none(call_id)?;
                 Err(e)
             }
-            None => self.call_result_none(call_id),
+            None => {
+                // Release the host iterator as soon as it's exhausted rather than
+                // holding on to it until the query is dropped.
+                self.iterators.remove(&call_id);
+                self.call_result_none(call_id)
+            }
         }
     }
 
@@ -185,14 +358,32 @@ impl Query {
             return lazy_error!("Invalid call error: kwargs not supported in Rust.");
         }
         tracing::trace!(call_id, name = %name, args = ?args, "call");
+        let instance_id = match instance.value() {
+            Value::ExternalInstance(ExternalInstance { instance_id, .. }) => Some(*instance_id),
+            _ => None,
+        };
+        if self.dry_run {
+            let args = args
+                .map(|args| {
+                    args.iter()
+                        .map(|v| PolarValue::from_term(v, &self.host))
+                        .collect::<crate::Result<Vec<PolarValue>>>()
+                })
+                .transpose()?;
+            self.plan_host_call(instance_id, &name.0, args);
+            let placeholder = format!("_dry_run_{}", call_id);
+            return self.call_result(call_id, PolarValue::Variable(placeholder));
+        }
         let instance = Instance::from_polar(PolarValue::from_term(&instance, &self.host)?)?;
         let result = if let Some(args) = args {
             let args = args
                 .iter()
                 .map(|v| PolarValue::from_term(v, &self.host))
                 .collect::<crate::Result<Vec<PolarValue>>>()?;
+            self.observe_host_call(instance_id, &name.0, Some(&args));
             instance.call(&name.0, args, &mut self.host)
         } else {
+            self.observe_host_call(instance_id, &name.0, None);
             instance.get_attr(&name.0, &mut self.host)
         };
         match result {
@@ -204,6 +395,34 @@ impl Query {
         }
     }
 
+    fn plan_host_call(
+        &mut self,
+        instance_id: Option<u64>,
+        method: &str,
+        args: Option<Vec<PolarValue>>,
+    ) {
+        self.planned_host_calls.push(crate::HostCall {
+            instance_id,
+            method: method.to_owned(),
+            args,
+        });
+    }
+
+    fn observe_host_call(
+        &self,
+        instance_id: Option<u64>,
+        method: &str,
+        args: Option<&[PolarValue]>,
+    ) {
+        if let Some(observer) = &self.host.host_call_observer {
+            observer(&crate::HostCall {
+                instance_id,
+                method: method.to_owned(),
+                args: args.map(<[PolarValue]>::to_vec),
+            });
+        }
+    }
+
     fn handle_external_op(
         &mut self,
         call_id: u64,
@@ -222,6 +441,22 @@ impl Query {
         Ok(())
     }
 
+    fn handle_external_operator(
+        &mut self,
+        call_id: u64,
+        name: Symbol,
+        args: Vec<Term>,
+    ) -> crate::Result<()> {
+        assert_eq!(args.len(), 2);
+        let operator = match self.host.operators.get(&name.0) {
+            Some(operator) => operator.clone(),
+            None => return lazy_error!("No operator called {} has been registered", name),
+        };
+        let left = PolarValue::from_term(&args[0], &self.host)?;
+        let right = PolarValue::from_term(&args[1], &self.host)?;
+        self.question_result(call_id, operator(left, right))
+    }
+
     fn handle_external_isa(
         &mut self,
         call_id: u64,
@@ -316,6 +551,26 @@ This may mean you performed an operation in your policy over an unbound variable
             .map(|t| PolarValue::from_term(t, &self.host).unwrap())
     }
 
+    /// Return every binding in the result, converted to a `PolarValue`.
+    pub fn bindings(&self) -> crate::Result<HashMap<String, PolarValue>> {
+        self.bindings
+            .iter()
+            .map(|(k, v)| Ok((k.0.clone(), PolarValue::from_term(v, &self.host)?)))
+            .collect()
+    }
+
+    /// Get the binding for `name` and convert it to `T`.
+    ///
+    /// ```
+    /// # use oso::Oso;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let oso = Oso::new();
+    /// let result = oso.query("x = 1 and y = \"one\"")?.next().unwrap()?;
+    /// assert_eq!(result.get_typed::<i64>("x")?, 1);
+    /// assert_eq!(result.get_typed::<String>("y")?, "one");
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn get_typed<T: crate::host::FromPolar>(&self, name: &str) -> crate::Result<T> {
         self.get(name)
             .ok_or(crate::OsoError::FromPolar)
@@ -1,5 +1,8 @@
 #![allow(dead_code)]
-use oso::Oso;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use oso::{Oso, PolarClass};
 
 pub struct OsoTest {
     pub oso: Oso,
@@ -93,6 +96,45 @@ impl OsoTest {
         assert_eq!(res.len(), 1, "expected exactly one result");
         assert_eq!(res.pop().unwrap(), expected);
     }
+
+    /// Register `Echo`, whose `echo` class method returns its argument.
+    #[track_caller]
+    pub fn register_echo(&mut self) {
+        self.oso
+            .register_class(
+                Echo::get_polar_class_builder()
+                    .add_class_method("echo", |n: i64| n)
+                    .build(),
+            )
+            .unwrap();
+    }
+
+    /// Count the host calls made from now on.
+    pub fn count_host_calls(&mut self) -> CallCount {
+        let calls = CallCount::default();
+        let counter = calls.clone();
+        self.oso.set_host_call_observer(move |_| {
+            counter.0.fetch_add(1, Ordering::SeqCst);
+        });
+        calls
+    }
+}
+
+#[derive(Clone, PolarClass)]
+pub struct Echo;
+
+/// A count of host calls, shared with the observer that increments it.
+#[derive(Clone, Default)]
+pub struct CallCount(Arc<AtomicUsize>);
+
+impl CallCount {
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub fn reset(&self) {
+        self.0.store(0, Ordering::SeqCst);
+    }
 }
 
 /// Pretest setup.
@@ -421,6 +421,108 @@ fn test_match_attribute_does_not_exist() {
     oso.qeval("no_match_foo(new Foo())");
 }
 
+/// Test that host errors abort the whole query when failing fast
+#[test]
+fn test_fail_fast_on_host_error() {
+    common::setup();
+
+    let mut oso = OsoTest::new();
+
+    #[derive(PolarClass)]
+    struct Foo {
+        #[polar(attribute)]
+        x: i64,
+    }
+
+    impl Foo {
+        fn new() -> Self {
+            Foo { x: 1 }
+        }
+    }
+
+    let foo_class = Foo::get_polar_class_builder()
+        .set_constructor(Foo::new)
+        .build();
+
+    oso.oso.register_class(foo_class).unwrap();
+
+    oso.load_str(
+        r#"check(d, 1) if d matches Foo{x: 1};
+           check(d, 2) if d matches Foo{not_an_attr: 1};
+           check(_, 3);"#,
+    );
+
+    // By default, the erroring branch is pruned.
+    assert_eq!(oso.qvar::<i64>("check(new Foo(), n)", "n"), vec![1, 3]);
+
+    let mut query = oso.oso.query("check(new Foo(), n)").unwrap();
+    query.set_fail_fast_on_host_error(true);
+    assert_eq!(
+        query
+            .next()
+            .unwrap()
+            .unwrap()
+            .get_typed::<i64>("n")
+            .unwrap(),
+        1
+    );
+    assert!(matches!(
+        query.next().unwrap().unwrap_err(),
+        OsoError::InvalidCallError { .. }
+    ));
+}
+
+/// Test that a query timeout covers slow host calls.
+#[test]
+fn test_query_timeout() -> oso::Result<()> {
+    common::setup();
+
+    use std::time::Duration;
+
+    #[derive(Clone, PolarClass)]
+    struct Slow;
+
+    let mut oso = OsoTest::new();
+    oso.oso.register_class(
+        Slow::get_polar_class_builder()
+            .add_class_method("wait", |n: i64| {
+                std::thread::sleep(Duration::from_millis(20));
+                n
+            })
+            .build(),
+    )?;
+    oso.load_str("f(x) if n in [1, 2, 3, 4, 5, 6, 7, 8, 9, 10] and x = Slow.wait(n);");
+
+    // Each call is fast enough on its own, but the query as a whole isn't.
+    let mut query = oso.oso.query("f(x)")?;
+    query.set_timeout(Some(Duration::from_millis(50)));
+    let mut found = 0;
+    let error = loop {
+        match query.next().unwrap() {
+            Ok(_) => found += 1,
+            Err(e) => break e,
+        }
+    };
+    assert!(
+        matches!(
+            &error,
+            OsoError::Polar(PolarError(ErrorKind::Runtime(RuntimeError::QueryTimeout {
+                timeout: 50,
+                ..
+            })))
+        ),
+        "{} doesn't match expected error",
+        error
+    );
+    assert!(found < 10);
+
+    // Without a timeout, every call completes.
+    let mut query = oso.oso.query("f(x)")?;
+    query.set_timeout(None);
+    assert_eq!(query.collect::<oso::Result<Vec<_>>>()?.len(), 10);
+    Ok(())
+}
+
 /// Test that match with class that doesn't exist raises error
 #[test]
 fn test_match_non_existent_class() {
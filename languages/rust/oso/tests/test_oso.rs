@@ -117,6 +117,24 @@ fn test_query_rule() -> oso::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_authorize() -> oso::Result<()> {
+    common::setup();
+    let oso = test_oso();
+
+    let actor = User::new(String::from("guest"));
+    let query = oso.query_rule("allow", (actor.clone(), "get", Widget::new(1)))?;
+    assert!(query.authorize()?);
+
+    let query = oso.query_rule("allow", (actor, "not_allowed", Widget::new(1)))?;
+    assert!(!query.authorize()?);
+
+    // Errors are not treated as a denial.
+    assert!(oso.query(r#"1 < "a""#)?.authorize().is_err());
+
+    Ok(())
+}
+
 #[test]
 fn test_fail() -> oso::Result<()> {
     common::setup();
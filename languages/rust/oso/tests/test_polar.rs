@@ -378,10 +378,34 @@ fn test_register_constant() -> oso::Result<()> {
     Ok(())
 }
 
-#[ignore]
 #[test]
-fn test_host_method_string() {
-    todo!();
+fn test_host_method_string() -> oso::Result<()> {
+    common::setup();
+
+    let mut oso = test_oso();
+    oso.load_str(
+        r#"admin_email(email) if email.ends_with("@example.com");
+           greeting(name, g) if g = "hello, " + name;"#,
+    );
+
+    oso.qeval(r#""abc".starts_with("ab")"#);
+    oso.qnull(r#""abc".starts_with("bc")"#);
+    oso.qeval(r#""abc".contains("b")"#);
+    assert_eq!(oso.qvar::<i64>(r#"x = "abc".len()"#, "x"), vec![3]);
+    assert_eq!(
+        oso.qvar::<Vec<String>>(r#"x = "a,b".split(",")"#, "x"),
+        vec![vec!["a".to_string(), "b".to_string()]]
+    );
+
+    oso.qeval(r#"admin_email("alice@example.com")"#);
+    oso.qnull(r#"admin_email("alice@example.org")"#);
+    oso.qeval(r#""alice" matches String"#);
+    assert_eq!(
+        oso.qvar::<String>(r#"greeting("alice", g)"#, "g"),
+        vec!["hello, alice".to_string()]
+    );
+
+    Ok(())
 }
 
 #[ignore]
@@ -802,6 +826,37 @@ fn test_variables_as_arguments() -> oso::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_variables_as_arguments_are_resolved() -> oso::Result<()> {
+    common::setup();
+
+    let mut oso = test_oso();
+    oso.load_str(
+        r#"allow(actor, "read", resource) if actor = "alice" and resource = {owner: actor};
+           allow("bob", "read", "public");"#,
+    );
+
+    let query = oso.oso.query_rule(
+        "allow",
+        (
+            PolarValue::Variable("a".to_owned()),
+            "read",
+            PolarValue::Variable("r".to_owned()),
+        ),
+    )?;
+    let results = query.collect::<oso::Result<Vec<_>>>()?;
+    assert_eq!(results.len(), 2);
+
+    assert_eq!(results[0].get_typed::<String>("a")?, "alice");
+    let resource = results[0].get_typed::<HashMap<String, String>>("r")?;
+    assert_eq!(resource["owner"], "alice");
+
+    assert_eq!(results[1].get_typed::<String>("a")?, "bob");
+    assert_eq!(results[1].get_typed::<String>("r")?, "public");
+
+    Ok(())
+}
+
 // Skipped test_stack_trace, this is functionality that should be tested in core.
 // TODO ^
 
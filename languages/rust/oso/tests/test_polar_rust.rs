@@ -67,6 +67,928 @@ fn test_data_conversions() {
     assert!(bool::try_from(x.remove(0)).unwrap());
 }
 
+#[test]
+fn test_result_bindings() {
+    common::setup();
+
+    use oso::PolarValue;
+
+    let mut test = OsoTest::new();
+    test.load_str(r#"f(1, "two", [x]) if x = 3;"#);
+    let mut results = test.query("f(a, b, c)");
+    let bindings = results.pop().unwrap().bindings().unwrap();
+    assert_eq!(
+        bindings,
+        hashmap! {
+            "a".to_string() => PolarValue::Integer(1),
+            "b".to_string() => PolarValue::String("two".to_string()),
+            "c".to_string() => PolarValue::List(vec![PolarValue::Integer(3)]),
+        }
+    );
+}
+
+#[test]
+fn test_query_pipeline() -> oso::Result<()> {
+    common::setup();
+
+    #[derive(Clone, PolarClass)]
+    struct User {
+        #[polar(attribute)]
+        name: String,
+    }
+
+    let mut test = OsoTest::new();
+    test.oso.register_class(
+        User::get_polar_class_builder()
+            .set_constructor(|name: String| User { name })
+            .build(),
+    )?;
+    test.load_str(
+        r#"user(u) if u = new User("alice") or u = new User("bob");
+           can(u, "read") if u.name = "alice";
+           can(_, "list");"#,
+    );
+
+    let results = test.oso.query_pipeline(&["user(u)", "can(u, action)"])?;
+    let combined = results
+        .iter()
+        .map(|r| {
+            let user = r.get_typed::<User>("u")?;
+            Ok((user.name, r.get_typed::<String>("action")?))
+        })
+        .collect::<oso::Result<Vec<_>>>()?;
+    assert_eq!(
+        combined,
+        vec![
+            ("alice".to_owned(), "read".to_owned()),
+            ("alice".to_owned(), "list".to_owned()),
+            ("bob".to_owned(), "list".to_owned()),
+        ]
+    );
+
+    assert!(test
+        .oso
+        .query_pipeline(&["user(u)", "u.name = \"carol\""])?
+        .is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_query_deltas() -> oso::Result<()> {
+    common::setup();
+
+    let mut test = OsoTest::new();
+    test.load_str(r#"f("alice", 1); f("alice", 2); f("bob", 2);"#);
+
+    let deltas = test
+        .oso
+        .query("f(name, n)")?
+        .deltas()
+        .map(|r| {
+            let mut keys = r?.keys().map(str::to_owned).collect::<Vec<_>>();
+            keys.sort();
+            Ok(keys)
+        })
+        .collect::<oso::Result<Vec<_>>>()?;
+    assert_eq!(
+        deltas,
+        vec![
+            vec!["n".to_owned(), "name".to_owned()],
+            vec!["n".to_owned()],
+            vec!["name".to_owned()],
+        ]
+    );
+
+    let mut deltas = test.oso.query("f(name, n)")?.deltas();
+    let second = deltas.nth(1).unwrap()?;
+    assert_eq!(second.get_typed::<i64>("n")?, 2);
+    assert!(second.get("name").is_none());
+    Ok(())
+}
+
+#[test]
+fn test_query_rule_batch() -> oso::Result<()> {
+    common::setup();
+
+    #[derive(Clone, PolarClass)]
+    struct Document {
+        #[polar(attribute)]
+        id: i64,
+    }
+
+    let mut test = OsoTest::new();
+    test.oso.register_class(Document::get_polar_class())?;
+    test.load_str(
+        r#"allow("alice", "read", r: Document) if r.id mod 2 = 0;
+           allow("alice", "read", r: Document) if r.id mod 3 = 0;"#,
+    );
+
+    let results = test.oso.query_rule_batch(
+        "allow",
+        (0..100).map(|id| ("alice", "read", Document { id })),
+    )?;
+    assert_eq!(results.len(), 100);
+    for (id, result) in results.iter().enumerate() {
+        let expected = (id % 2 == 0) as usize + (id % 3 == 0) as usize;
+        assert_eq!(result.len(), expected, "resource {}", id);
+    }
+
+    let results = test
+        .oso
+        .query_rule_batch("allow", vec![("bob", "read", Document { id: 0 })])?;
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_try_for_each_result() -> oso::Result<()> {
+    common::setup();
+
+    let mut test = OsoTest::new();
+    test.load_str("f(1); f(2); f(3); f(4);");
+
+    let mut seen = vec![];
+    test.oso.query("f(x)")?.try_for_each_result(|result| {
+        seen.push(result.get_typed::<i64>("x")?);
+        Ok::<_, oso::OsoError>(())
+    })?;
+    assert_eq!(seen, vec![1, 2, 3, 4]);
+
+    // The callback can abort the query.
+    let mut seen = vec![];
+    let err = test
+        .oso
+        .query("f(x)")?
+        .try_for_each_result(|result| {
+            if seen.len() == 2 {
+                return Err(oso::OsoError::Custom {
+                    message: "seen enough".to_owned(),
+                });
+            }
+            seen.push(result.get_typed::<i64>("x")?);
+            Ok(())
+        })
+        .unwrap_err();
+    assert_eq!(seen, vec![1, 2]);
+    assert!(matches!(err, oso::OsoError::Custom { message } if message == "seen enough"));
+    Ok(())
+}
+
+#[test]
+fn test_run_batched() -> oso::Result<()> {
+    common::setup();
+
+    let mut test = OsoTest::new();
+    test.register_echo();
+    let calls = test.count_host_calls();
+    test.load_str(
+        r#"f(x) if n in [1, 2, 3, 4, 5] and x = Echo.echo(n);
+           g(x) if x in [1, 2, "three"] and x + 1 > 0;"#,
+    );
+
+    let values = |batch: Vec<oso::ResultSet>| {
+        batch
+            .iter()
+            .map(|result| result.get_typed::<i64>("x").unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    // Batches are only computed as they are pulled.
+    let mut batches = test.oso.query("f(x)")?.run_batched(2);
+    assert_eq!(calls.get(), 0);
+    assert_eq!(values(batches.next().unwrap()?), vec![1, 2]);
+    assert_eq!(calls.get(), 2);
+    assert_eq!(values(batches.next().unwrap()?), vec![3, 4]);
+    assert_eq!(calls.get(), 4);
+    assert_eq!(values(batches.next().unwrap()?), vec![5]);
+    assert!(batches.next().is_none());
+
+    // Results gathered before an error are yielded ahead of it.
+    let mut batches = test.oso.query("g(x)")?.run_batched(5);
+    assert_eq!(values(batches.next().unwrap()?), vec![1, 2]);
+    assert!(batches.next().unwrap().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_not_stops_at_first_result() -> oso::Result<()> {
+    common::setup();
+
+    let mut test = OsoTest::new();
+    test.register_echo();
+    let calls = test.count_host_calls();
+    test.load_str("many(x) if n in [1, 2, 3, 4, 5] and x = Echo.echo(n);");
+
+    // The negated goal has five results, but the first one is enough to
+    // fail the negation.
+    test.qnull("not many(_)");
+    assert_eq!(calls.get(), 1);
+
+    calls.reset();
+    test.qeval("not many(6)");
+    assert_eq!(calls.get(), 5);
+    Ok(())
+}
+
+#[test]
+fn test_assert_calls_host_once() -> oso::Result<()> {
+    common::setup();
+
+    let mut test = OsoTest::new();
+    test.register_echo();
+    let calls = test.count_host_calls();
+
+    // The condition is only evaluated once.
+    test.qeval("assert(n in [1] and Echo.echo(n) = 1)");
+    assert_eq!(calls.get(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_run_grouped_by() -> oso::Result<()> {
+    common::setup();
+
+    let mut test = OsoTest::new();
+    test.load_str(
+        r#"allowed("read", "doc1");
+           allowed("write", "doc1");
+           allowed("read", "doc2");
+           allowed("admin", _resource);"#,
+    );
+
+    let actions = |results: &Vec<oso::ResultSet>| {
+        results
+            .iter()
+            .map(|result| result.get_typed::<String>("action").unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    let groups = test
+        .oso
+        .query("allowed(action, resource)")?
+        .run_grouped_by::<String>("resource")?;
+    assert_eq!(groups.len(), 3);
+    assert_eq!(
+        actions(&groups[&Some("doc1".to_owned())]),
+        vec!["read", "write"]
+    );
+    assert_eq!(actions(&groups[&Some("doc2".to_owned())]), vec!["read"]);
+    // An unbound key is grouped under `None`.
+    assert_eq!(actions(&groups[&None]), vec!["admin"]);
+
+    // A key that can't be converted is an error.
+    assert!(test
+        .oso
+        .query("allowed(action, resource)")?
+        .run_grouped_by::<i64>("resource")
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_compare_attributes() -> oso::Result<()> {
+    common::setup();
+
+    #[derive(Clone, PolarClass)]
+    struct Document {
+        #[polar(attribute)]
+        size: i64,
+        #[polar(attribute)]
+        page_sizes: Vec<i64>,
+    }
+
+    let mut test = OsoTest::new();
+    test.oso.register_class(Document::get_polar_class())?;
+    test.load_str(
+        r#"small(d: Document) if d.size < 100;
+           has_small_page(d: Document) if s in d.page_sizes and s < 10;"#,
+    );
+
+    let doc = |size, page_sizes| Document { size, page_sizes };
+    let count = |rule, d| test.oso.query_rule(rule, (d,)).unwrap().count();
+    assert_eq!(count("small", doc(99, vec![])), 1);
+    assert_eq!(count("small", doc(100, vec![])), 0);
+
+    // Multi-valued attributes are compared one value at a time.
+    assert_eq!(count("has_small_page", doc(0, vec![20, 5, 30])), 1);
+    assert_eq!(count("has_small_page", doc(0, vec![5, 1])), 2);
+    assert_eq!(count("has_small_page", doc(0, vec![20, 30])), 0);
+    Ok(())
+}
+
+#[test]
+fn test_undefined_rule_is_recoverable() -> oso::Result<()> {
+    common::setup();
+
+    let mut test = OsoTest::new();
+    test.load_str("f(1);");
+
+    let mut query = test.oso.query_rule("g", (1,))?;
+    let err = query.next().unwrap().unwrap_err();
+    assert!(
+        err.to_string().contains("Query for undefined rule `g`"),
+        "{}",
+        err
+    );
+
+    // The same Oso instance can carry on querying afterwards.
+    assert_eq!(test.oso.query_rule("f", (1,))?.count(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_matches_union_of_classes() -> oso::Result<()> {
+    common::setup();
+
+    #[derive(Clone, PolarClass)]
+    struct Admin;
+    #[derive(Clone, PolarClass)]
+    struct Manager;
+    #[derive(Clone, PolarClass)]
+    struct Guest;
+
+    let mut test = OsoTest::new();
+    test.oso.register_class(Admin::get_polar_class())?;
+    test.oso.register_class(Manager::get_polar_class())?;
+    test.oso.register_class(Guest::get_polar_class())?;
+    test.load_str("privileged(user) if user matches (Admin or Manager);");
+
+    assert_eq!(test.oso.query_rule("privileged", (Manager,))?.count(), 1);
+    assert_eq!(test.oso.query_rule("privileged", (Admin,))?.count(), 1);
+    assert_eq!(test.oso.query_rule("privileged", (Guest,))?.count(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_matches_union_calls_host_once() -> oso::Result<()> {
+    common::setup();
+
+    let mut test = OsoTest::new();
+    test.register_echo();
+    let calls = test.count_host_calls();
+
+    // The left side is evaluated once, not once per pattern.
+    test.qeval("Echo.echo(1) matches (String or Integer)");
+    assert_eq!(calls.get(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_bind_instance_after_matches() -> oso::Result<()> {
+    common::setup();
+
+    #[derive(Clone, PolarClass)]
+    struct User {
+        #[polar(attribute)]
+        name: String,
+    }
+
+    #[derive(Clone, PolarClass)]
+    struct Robot;
+
+    let mut test = OsoTest::new();
+    test.oso.register_class(User::get_polar_class())?;
+    test.oso.register_class(Robot::get_polar_class())?;
+    test.load_str(
+        r#"user_named(x, name) if x matches User and x = u and u.name = name;
+           typed(y) if x matches User and x = y;"#,
+    );
+
+    let user = User {
+        name: "sam".to_owned(),
+    };
+
+    // A matched instance can be aliased and used as usual.
+    let mut results = test.oso.query_rule(
+        "user_named",
+        (user.clone(), oso::PolarValue::Variable("n".to_owned())),
+    )?;
+    let name: String = results.next().unwrap()?.get_typed("n")?;
+    assert_eq!(name, "sam");
+    assert!(results.next().is_none());
+
+    // Binding a variable constrained by `matches` checks the instance
+    // against the class with the host.
+    assert_eq!(test.oso.query_rule("typed", (user,))?.count(), 1);
+    assert_eq!(test.oso.query_rule("typed", (Robot,))?.count(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_matches_instance_literal_fields() -> oso::Result<()> {
+    common::setup();
+
+    use oso::PolarValue;
+
+    #[derive(Clone, PolarClass)]
+    struct Member {
+        #[polar(attribute)]
+        role: String,
+        #[polar(attribute)]
+        team: String,
+    }
+
+    let mut test = OsoTest::new();
+    test.oso.register_class(Member::get_polar_class())?;
+    test.load_str(
+        r#"is_admin(m) if m matches Member{role: "admin"};
+           role_of(m, r) if m matches Member{role: r};
+           on_team(m, t) if m matches Member{role: "admin", team: t};
+           has_level(m) if m matches Member{level: 1};"#,
+    );
+
+    let admin = Member {
+        role: "admin".to_owned(),
+        team: "core".to_owned(),
+    };
+    let guest = Member {
+        role: "guest".to_owned(),
+        team: "core".to_owned(),
+    };
+
+    // Fields in the pattern must unify with the instance's attributes;
+    // attributes the pattern doesn't mention are ignored.
+    assert_eq!(
+        test.oso.query_rule("is_admin", (admin.clone(),))?.count(),
+        1
+    );
+    assert_eq!(
+        test.oso.query_rule("is_admin", (guest.clone(),))?.count(),
+        0
+    );
+
+    // Variables in field position are bound to the attribute value.
+    let mut results = test.oso.query_rule(
+        "role_of",
+        (guest.clone(), PolarValue::Variable("r".to_owned())),
+    )?;
+    let role: String = results.next().unwrap()?.get_typed("r")?;
+    assert_eq!(role, "guest");
+    assert!(results.next().is_none());
+
+    let mut results = test
+        .oso
+        .query_rule("on_team", (admin, PolarValue::Variable("t".to_owned())))?;
+    let team: String = results.next().unwrap()?.get_typed("t")?;
+    assert_eq!(team, "core");
+    assert_eq!(
+        test.oso
+            .query_rule(
+                "on_team",
+                (guest.clone(), PolarValue::Variable("t".to_owned()))
+            )?
+            .count(),
+        0
+    );
+
+    // A field the instance doesn't have fails the match.
+    assert_eq!(test.oso.query_rule("has_level", (guest,))?.count(), 0);
+
+    // Dictionaries match a dictionary pattern's fields the same way.
+    test.qvar_one(
+        r#"{role: "admin", team: "core"} matches {role: r}"#,
+        "r",
+        "admin".to_owned(),
+    );
+    test.qnull(r#"{team: "core"} matches {role: _r}"#);
+    Ok(())
+}
+
+#[test]
+fn test_register_operator() -> oso::Result<()> {
+    common::setup();
+
+    use oso::PolarValue;
+
+    let mut test = OsoTest::new();
+    test.oso
+        .register_operator("in_cidr", |ip: PolarValue, block: PolarValue| {
+            match (ip, block) {
+                (PolarValue::String(ip), PolarValue::String(block)) => {
+                    // Only whole octets, which is enough for a test.
+                    let (network, bits) = block.split_once('/').unwrap();
+                    let octets = bits.parse::<usize>().unwrap() / 8;
+                    ip.split('.')
+                        .take(octets)
+                        .eq(network.split('.').take(octets))
+                }
+                _ => false,
+            }
+        })?;
+    test.load_str(
+        r#"internal(ip) if in_cidr(ip, "10.0.0.0/16");
+           trusted(ip) if in_cidr(ip, "192.168.0.0/24") or internal(ip);"#,
+    );
+
+    test.qeval(r#"internal("10.0.3.4")"#);
+    test.qnull(r#"internal("10.1.3.4")"#);
+    test.qeval(r#"trusted("192.168.0.7")"#);
+    test.qeval(r#"trusted("10.0.0.1")"#);
+    test.qnull(r#"trusted("8.8.8.8")"#);
+
+    // Operators are binary, and their arguments must be bound.
+    test.query_err(r#"in_cidr("10.0.0.1")"#);
+    test.query_err(r#"in_cidr(ip, "10.0.0.0/16")"#);
+
+    // Registering the same operator twice is an error.
+    assert!(test.oso.register_operator("in_cidr", |_, _| true).is_err());
+
+    // Built-in operators and keywords can't be replaced.
+    assert!(test.oso.register_operator("matches", |_, _| true).is_err());
+    assert!(test.oso.register_operator("print", |_, _| true).is_err());
+
+    // Rules take precedence over an operator with the same name.
+    let mut test = OsoTest::new();
+    test.oso.register_operator("related", |_, _| true)?;
+    test.load_str("related(1, 2);");
+    test.qeval("related(1, 2)");
+    test.qnull("related(1, 3)");
+
+    // Names don't have to be static.
+    let name = String::from("always");
+    test.oso.register_operator(&name, |_, _| true)?;
+    test.qeval("always(1, 2)");
+
+    // A query only sees the operators registered when it started.
+    let mut query = test.oso.query("late(1, 2)")?;
+    test.oso.register_operator("late", |_, _| true)?;
+    let err = query.next().unwrap().unwrap_err().to_string();
+    assert!(err.contains("undefined rule `late`"), "{}", err);
+    test.qeval("late(1, 2)");
+    Ok(())
+}
+
+#[test]
+fn test_host_call_observer() -> oso::Result<()> {
+    common::setup();
+
+    use oso::PolarValue;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, PolarClass)]
+    struct User {
+        #[polar(attribute)]
+        name: String,
+    }
+
+    impl User {
+        fn can(&self, action: String, level: i64) -> bool {
+            action == "read" && level < 3
+        }
+    }
+
+    let calls = Arc::new(Mutex::new(vec![]));
+    let mut test = OsoTest::new();
+    test.oso.register_class(
+        User::get_polar_class_builder()
+            .set_constructor(|name: String| User { name })
+            .add_method("can", User::can)
+            .build(),
+    )?;
+    let recorded = calls.clone();
+    test.oso.set_host_call_observer(move |call| {
+        recorded.lock().unwrap().push(call.clone());
+    });
+    test.load_str(
+        r#"allowed(name) if
+               u = new User(name) and u.name = "alice" and u.can("read", 2);"#,
+    );
+    assert_eq!(test.query(r#"allowed("alice")"#).len(), 1);
+
+    let calls = calls.lock().unwrap();
+    let summary = calls
+        .iter()
+        .map(|call| (call.method.as_str(), call.args.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![
+            ("User", Some(vec![PolarValue::String("alice".to_owned())])),
+            ("name", None),
+            (
+                "can",
+                Some(vec![
+                    PolarValue::String("read".to_owned()),
+                    PolarValue::Integer(2)
+                ])
+            ),
+        ]
+    );
+    // Every call is on the new instance.
+    assert!(calls
+        .iter()
+        .all(|call| call.instance_id == calls[0].instance_id));
+    assert!(calls[0].instance_id.is_some());
+    Ok(())
+}
+
+#[test]
+fn test_numeric_methods() {
+    common::setup();
+
+    let mut test = OsoTest::new();
+    test.qvar_one("f = 1.7 and x = f.floor()", "x", 1);
+    test.qvar_one("f = 1.2 and x = f.ceil()", "x", 2);
+    test.qvar_one("f = 2.5 and x = f.round()", "x", 3);
+    test.qvar_one("f = -2.5 and x = f.round()", "x", -3);
+    test.qvar_one("f = -1.5 and x = f.abs()", "x", 1.5);
+
+    // Integers are already whole.
+    test.qvar_one("i = 7 and x = i.floor()", "x", 7);
+    test.qvar_one("i = 7 and x = i.round()", "x", 7);
+    test.qvar_one("i = -7 and x = i.abs()", "x", 7);
+
+    let results = test.query("f = 1.7 and x = f.floor() and x matches Integer");
+    assert_eq!(results.len(), 1);
+
+    // Values that don't fit in an integer are errors rather than saturating.
+    for q in [
+        "x = nan.floor()",
+        "x = inf.ceil()",
+        "x = (-inf).round()",
+        "x = 1e19.floor()",
+        "x = (-1e19).ceil()",
+    ] {
+        let err = test.query_err(q);
+        assert!(err.contains("cannot be converted to an Integer"), "{}", err);
+    }
+    let err = test.query_err("i = -9223372036854775807 - 1 and x = i.abs()");
+    assert!(err.contains("overflows an Integer"), "{}", err);
+}
+
+#[test]
+fn test_dry_run() -> oso::Result<()> {
+    common::setup();
+
+    use oso::PolarValue;
+
+    #[derive(Clone, PolarClass)]
+    struct User {
+        #[polar(attribute)]
+        name: String,
+    }
+
+    #[derive(Clone, PolarClass)]
+    struct Document {
+        #[polar(attribute)]
+        owner: String,
+    }
+
+    impl User {
+        fn has_role(&self, _role: String) -> bool {
+            true
+        }
+    }
+
+    let mut test = OsoTest::new();
+    test.oso.register_class(
+        User::get_polar_class_builder()
+            .add_method("has_role", User::has_role)
+            .build(),
+    )?;
+    test.oso.register_class(Document::get_polar_class())?;
+    let calls = test.count_host_calls();
+    test.load_str(
+        r#"allow(u: User, "read", d: Document) if d.owner = u.name and u.has_role("reader");"#,
+    );
+
+    let user = User {
+        name: "alice".to_owned(),
+    };
+    let doc = Document {
+        owner: "bob".to_owned(),
+    };
+    let mut query = test.oso.query_rule("allow", (user, "read", doc))?;
+    query.set_dry_run(true);
+    while let Some(result) = query.next_result() {
+        result?;
+    }
+
+    let plan = query
+        .planned_host_calls()
+        .iter()
+        .map(|call| (call.method.as_str(), call.args.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        plan,
+        vec![
+            ("owner", None),
+            ("name", None),
+            (
+                "has_role",
+                Some(vec![PolarValue::String("reader".to_owned())])
+            ),
+        ]
+    );
+    assert_eq!(calls.get(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_dry_run_constructor() -> oso::Result<()> {
+    common::setup();
+
+    use oso::PolarValue;
+
+    #[derive(Clone, PolarClass)]
+    struct User {
+        #[polar(attribute)]
+        name: String,
+    }
+
+    impl User {
+        fn new(name: String) -> Self {
+            Self { name }
+        }
+
+        fn has_role(&self, _role: String) -> bool {
+            true
+        }
+    }
+
+    let mut test = OsoTest::new();
+    test.oso.register_class(
+        User::get_polar_class_builder()
+            .set_constructor(User::new)
+            .add_method("has_role", User::has_role)
+            .build(),
+    )?;
+
+    // Calling a method on a constructed instance is planned like any other call.
+    let mut query = test
+        .oso
+        .query(r#"u = new User("alice") and u.has_role("reader")"#)?;
+    query.set_dry_run(true);
+    let mut results = 0;
+    while let Some(result) = query.next_result() {
+        let result = result?;
+        assert!(matches!(result.get("u"), Some(PolarValue::Instance(_))));
+        results += 1;
+    }
+    assert_eq!(results, 1);
+
+    let plan = query
+        .planned_host_calls()
+        .iter()
+        .map(|call| (call.method.as_str(), call.args.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        plan,
+        vec![
+            ("User", Some(vec![PolarValue::String("alice".to_owned())])),
+            (
+                "has_role",
+                Some(vec![PolarValue::String("reader".to_owned())])
+            ),
+        ]
+    );
+
+    // The placeholder isn't an instance of the class it stands in for.
+    let mut query = test
+        .oso
+        .query(r#"u = new User("alice") and u matches User"#)?;
+    query.set_dry_run(true);
+    assert!(query.next_result().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_dict_equality_with_instances() -> oso::Result<()> {
+    common::setup();
+
+    use std::collections::HashMap;
+
+    // Owners compare equal by id, even when they are distinct instances.
+    #[derive(Clone, PolarClass)]
+    struct Owner {
+        id: i64,
+        #[polar(attribute)]
+        name: String,
+    }
+
+    impl PartialEq for Owner {
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    let mut test = OsoTest::new();
+    test.oso.register_class(
+        Owner::get_polar_class_builder()
+            .with_equality_check()
+            .build(),
+    )?;
+    test.load_str("same(a, b) if a == b;");
+
+    let owned_by = |id, name: &str| {
+        let mut dict = HashMap::new();
+        dict.insert(
+            "owner",
+            Owner {
+                id,
+                name: name.to_owned(),
+            },
+        );
+        dict
+    };
+
+    let results = test
+        .oso
+        .query_rule("same", (owned_by(1, "alice"), owned_by(1, "alicia")))?
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_ok());
+
+    let mut results = test
+        .oso
+        .query_rule("same", (owned_by(1, "alice"), owned_by(2, "alice")))?;
+    assert!(results.next().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_iterators_are_released() {
+    common::setup();
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// A host-side iterator that counts how many copies of it are alive.
+    struct Cursor {
+        open: Arc<AtomicUsize>,
+        next: u32,
+    }
+
+    impl Cursor {
+        fn new(open: Arc<AtomicUsize>) -> Self {
+            open.fetch_add(1, Ordering::SeqCst);
+            Self { open, next: 0 }
+        }
+    }
+
+    impl Clone for Cursor {
+        fn clone(&self) -> Self {
+            let mut cursor = Self::new(self.open.clone());
+            cursor.next = self.next;
+            cursor
+        }
+    }
+
+    impl Drop for Cursor {
+        fn drop(&mut self) {
+            self.open.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    impl Iterator for Cursor {
+        type Item = u32;
+        fn next(&mut self) -> Option<u32> {
+            self.next += 1;
+            Some(self.next).filter(|n| *n <= 3)
+        }
+    }
+
+    #[derive(Clone, PolarClass)]
+    struct Table;
+
+    let open = Arc::new(AtomicUsize::new(0));
+    let cursor_open = open.clone();
+
+    let mut test = OsoTest::new();
+    test.oso
+        .register_class(
+            Table::get_polar_class_builder()
+                .set_into_iter(move |_| Cursor::new(cursor_open.clone()))
+                .build(),
+        )
+        .unwrap();
+    test.oso.register_constant(Table, "table").unwrap();
+
+    // Cancelling a query mid-iteration releases the cursor.
+    let mut query = test.oso.query("x in table").unwrap();
+    assert_eq!(
+        query
+            .next()
+            .unwrap()
+            .unwrap()
+            .get_typed::<u32>("x")
+            .unwrap(),
+        1
+    );
+    assert_eq!(open.load(Ordering::SeqCst), 1);
+    drop(query);
+    assert_eq!(open.load(Ordering::SeqCst), 0);
+
+    // An exhausted cursor is released before the query finishes.
+    let mut query = test.oso.query("x in table").unwrap();
+    for _ in 0..3 {
+        query.next().unwrap().unwrap();
+    }
+    assert!(query.next().is_none());
+    assert_eq!(open.load(Ordering::SeqCst), 0);
+}
+
 // This logic is changing. Updated when fixed
 #[ignore]
 #[test]
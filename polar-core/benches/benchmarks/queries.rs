@@ -184,6 +184,69 @@ pub fn many_rules(c: &mut Criterion) {
     });
 }
 
+/// Bench: bind `a{i} = [a{i-1}, a{i-1}]` up to `DEPTH` so that every
+/// variable shares the structure of the previous one, and measure the
+/// time to construct the result.
+pub fn shared_structure(c: &mut Criterion) {
+    fn make_runner(depth: usize) -> Runner {
+        let mut query = "a0 = [1, 2, 3]".to_owned();
+        for i in 1..=depth {
+            query += &format!(" and a{} = [a{}, a{}]", i, i - 1, i - 1);
+        }
+        runner_from_query(&query)
+    }
+
+    let depth_array = [4, 8, 12];
+
+    let mut group = c.benchmark_group("shared_structure");
+    for depth in &depth_array {
+        group.bench_function(BenchmarkId::from_parameter(format!("{}", depth)), |b| {
+            b.iter_batched_ref(
+                || make_runner(*depth),
+                |runner| runner.run(),
+                criterion::BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+/// Bench: walk a chain of `parent` facts of length `n` with a recursive
+/// `ancestor` rule, where every node also has a dead-end child. This
+/// measures the cost of backtracking out of failed branches, which only
+/// unwinds the bindings made since the last choice point.
+pub fn ancestor(c: &mut Criterion) {
+    fn make_runner(n: usize) -> Runner {
+        let mut runner = runner_from_query(&format!(r#"ancestor("p0", "p{}")"#, n));
+        let mut policy = r#"ancestor(a, d) if parent(a, d);
+                            ancestor(a, d) if parent(a, c) and ancestor(c, d);"#
+            .to_owned();
+        for i in 0..n {
+            policy += &format!(r#"parent("p{}", "q{}");"#, i, i);
+            policy += &format!(r#"parent("p{}", "p{}");"#, i, i + 1);
+        }
+        runner.load_str(&policy).unwrap();
+        runner.expected_result(Bindings::new());
+        runner
+    }
+
+    let n_array = [10, 20, 40];
+
+    let mut group = c.benchmark_group("ancestor");
+    for n in &n_array {
+        group.bench_function(BenchmarkId::from_parameter(format!("{}", n)), |b| {
+            b.iter_batched_ref(
+                || make_runner(*n),
+                |runner| runner.run(),
+                criterion::BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
 fn load_policy(c: &mut Criterion) {
     let policy = include_str!("roles_policy.polar");
     c.bench_function("load_policy", |b| {
@@ -203,4 +266,6 @@ criterion_group!(
     indexed_rules,
     not,
     load_policy,
+    shared_structure,
+    ancestor,
 );
@@ -1,7 +1,9 @@
 /// Manage binding state in the VM.
 ///
 /// Bindings associate variables in the VM with constraints or values.
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 
 use crate::{
     error::{PolarResult, RuntimeError},
@@ -45,6 +47,13 @@ pub enum VariableState {
 struct Derefer<'a> {
     binding_manager: &'a BindingManager,
     seen: HashSet<u64>,
+    /// Fully dereferenced values of variables, so that structure shared
+    /// between several variables is only walked once.
+    resolved: HashMap<Symbol, Term>,
+    /// Number of times a cycle has been cut short. A variable's value is
+    /// only memoized if no cycle was cut short while walking it, since the
+    /// result otherwise depends on where the walk started.
+    cycles: usize,
 }
 
 impl<'a> Derefer<'a> {
@@ -52,6 +61,8 @@ impl<'a> Derefer<'a> {
         Self {
             binding_manager,
             seen: HashSet::new(),
+            resolved: HashMap::new(),
+            cycles: 0,
         }
     }
 }
@@ -75,14 +86,23 @@ impl<'a> Folder for Derefer<'a> {
         match t.value() {
             Value::Expression(_) => t,
             Value::Variable(v) | Value::RestVariable(v) => {
+                if let Some(resolved) = self.resolved.get(v) {
+                    return resolved.clone();
+                }
                 let hash = t.hash_value();
                 if self.seen.contains(&hash) {
+                    self.cycles += 1;
                     t
                 } else {
                     self.seen.insert(hash);
-                    let t = self.binding_manager.lookup(v).unwrap_or(t);
+                    let cycles = self.cycles;
+                    let v = v.clone();
+                    let t = self.binding_manager.lookup(&v).unwrap_or(t);
                     let t = fold_term(t, self);
                     self.seen.remove(&hash);
+                    if self.cycles == cycles {
+                        self.resolved.insert(v, t.clone());
+                    }
                     t
                 }
             }
@@ -91,6 +111,20 @@ impl<'a> Folder for Derefer<'a> {
     }
 }
 
+/// Hash `bindings` independently of the order of its entries, and
+/// consistently with equality of the bound values (e.g., `1` and `1.0`
+/// hash alike). Suitable as a cache key within a single build.
+pub fn hash_bindings(bindings: &Bindings) -> u64 {
+    let mut entries = bindings
+        .iter()
+        .map(|(var, value)| (var, value.hash_value()))
+        .collect::<Vec<_>>();
+    entries.sort();
+    let mut hasher = DefaultHasher::new();
+    entries.hash(&mut hasher);
+    hasher.finish()
+}
+
 /// Represent each binding in a cycle as a unification constraint.
 // TODO(gj): put this in an impl block on VariableState?
 fn cycle_constraints(cycle: Vec<Symbol>) -> Operation {
@@ -357,6 +391,40 @@ impl BindingManager {
         VariableState::Unbound
     }
 
+    /// Whether `var`, or any variable aliased to it, occurs in `term` once
+    /// bound variables are replaced by their values. Binding `var` to such a
+    /// term would make it cyclic.
+    pub fn occurs(&self, var: &Symbol, term: &Term) -> bool {
+        let aliases: HashSet<Symbol> = match self._variable_state(var) {
+            BindingManagerVariableState::Cycle(vars) => vars.into_iter().collect(),
+            _ => std::iter::once(var.clone()).collect(),
+        };
+        let mut seen = HashSet::new();
+        let mut stack = vec![term.clone()];
+        while let Some(term) = stack.pop() {
+            match term.value() {
+                Value::Variable(v) | Value::RestVariable(v) => {
+                    if aliases.contains(v) {
+                        return true;
+                    }
+                    if seen.insert(v.clone()) {
+                        if let VariableState::Bound(value) = self.variable_state(v) {
+                            stack.push(value);
+                        }
+                    }
+                }
+                Value::List(terms) => stack.extend(terms.iter().cloned()),
+                Value::Dictionary(dict) => stack.extend(dict.fields.values().cloned()),
+                Value::Call(call) => {
+                    stack.extend(call.args.iter().cloned());
+                    stack.extend(call.kwargs.iter().flat_map(|k| k.values().cloned()));
+                }
+                _ => (),
+            }
+        }
+        false
+    }
+
     /// Return all variables used in this binding manager.
     pub fn variables(&self) -> HashSet<Symbol> {
         self.bindings
@@ -365,6 +433,20 @@ impl BindingManager {
             .collect()
     }
 
+    /// Whether any variable may have been partially bound at `bsp`, i.e.,
+    /// bound to an expression or aliased to another variable. Conservative:
+    /// bindings that were later overwritten also count.
+    pub fn has_partials_at_point(&self, bsp: &Bsp) -> bool {
+        self.bindings[..bsp.bindings_index]
+            .iter()
+            .any(|Binding(_, value)| {
+                matches!(
+                    value.value(),
+                    Value::Expression(_) | Value::Variable(_) | Value::RestVariable(_)
+                )
+            })
+    }
+
     /// Retrieve an opaque value representing the current state of `BindingManager`.
     /// Can be used to reset state with `backtrack`.
     pub fn bsp(&self) -> Bsp {
@@ -385,22 +467,33 @@ impl BindingManager {
     }
 
     pub fn bindings_after(&self, include_temps: bool, after: &Bsp) -> Bindings {
+        let mut derefer = Derefer::new(self);
         let mut bindings = HashMap::new();
         for Binding(var, value) in &self.bindings[after.bindings_index..] {
             if !include_temps && var.is_temporary_var() {
                 continue;
             }
-            bindings.insert(var.clone(), self.deep_deref(value));
+            bindings.insert(var.clone(), derefer.fold_term(value.clone()));
         }
         bindings
     }
 
+    /// Like `bindings_after`, but with every value exactly as it was bound,
+    /// including temporaries and without dereferencing any variables.
+    pub fn raw_bindings_after(&self, after: &Bsp) -> Bindings {
+        self.bindings[after.bindings_index..]
+            .iter()
+            .map(|Binding(var, value)| (var.clone(), value.clone()))
+            .collect()
+    }
+
     pub fn variable_bindings(&self, variables: &HashSet<Symbol>) -> Bindings {
+        let mut derefer = Derefer::new(self);
         let mut bindings = HashMap::new();
         for var in variables.iter() {
             let value = self.value(var, self.bsp().bindings_index);
             if let Some(value) = value {
-                bindings.insert(var.clone(), self.deep_deref(value));
+                bindings.insert(var.clone(), derefer.fold_term(value.clone()));
             }
         }
         bindings
@@ -674,6 +767,37 @@ mod test {
         );
     }
 
+    #[test]
+    fn variable_state_follows_chains() {
+        let mut bindings = BindingManager::new();
+
+        let x = sym!("x");
+        let y = sym!("y");
+        let z = sym!("z");
+
+        // Chain ending in an unbound variable: every link is unbound.
+        bindings.add_binding(&x, term!(y.clone()));
+        bindings.add_binding(&y, term!(z.clone()));
+        for v in &[&x, &y, &z] {
+            assert_eq!(
+                bindings._variable_state(v),
+                BindingManagerVariableState::Unbound
+            );
+            assert_eq!(bindings.variable_state(v), VariableState::Unbound);
+        }
+
+        // Chain ending in a value: every link sees the value.
+        bindings.add_binding(&z, term!(1));
+        for v in &[&x, &y, &z] {
+            assert_eq!(
+                bindings._variable_state(v),
+                BindingManagerVariableState::Bound(term!(1))
+            );
+            assert_eq!(bindings.variable_state(v), VariableState::Bound(term!(1)));
+        }
+        assert_eq!(bindings.deep_deref(&term!(x)), term!(1));
+    }
+
     #[test]
     fn test_followers() {
         // Regular bindings
@@ -858,6 +982,47 @@ mod test {
         );
     }
 
+    #[test]
+    fn deep_deref_shared() {
+        let mut bm = BindingManager::default();
+        let (a, b, c) = (sym!("a"), sym!("b"), sym!("c"));
+        bm.bind(&a, term!([1, 2])).unwrap();
+        bm.bind(&b, term!([a.clone(), a.clone()])).unwrap();
+        bm.bind(&c, term!([b.clone(), a])).unwrap();
+        let bindings = bm.bindings(false);
+        let one_two = value!([1, 2]);
+        let b_value = value!([one_two.clone(), one_two.clone()]);
+        assert_eq!(bindings[&b], term!(b_value.clone()));
+        assert_eq!(bindings[&c], term!([b_value, one_two]));
+
+        // Values walked while a cycle was cut short aren't reused.
+        let (x, y) = (sym!("x"), sym!("y"));
+        bm.bind(&x, term!([y.clone()])).unwrap();
+        bm.bind(&y, term!([x.clone()])).unwrap();
+        let mut derefer = Derefer::new(&bm);
+        assert_eq!(derefer.fold_term(term!(x.clone())), term!([value!([x])]));
+        assert_eq!(derefer.fold_term(term!(y.clone())), term!([value!([y])]));
+    }
+
+    #[test]
+    fn test_hash_bindings() {
+        let parse = |src| crate::parser::parse_query(src).unwrap();
+        let x = parse("{a: 1, b: [2, {c: 3}]}");
+        let y = parse("{b: [2.0, {c: 3}], a: 1}");
+        assert_eq!(x.hash_value(), y.hash_value());
+
+        let one: Bindings = vec![(sym!("x"), x), (sym!("z"), term!(1))]
+            .into_iter()
+            .collect();
+        let two: Bindings = vec![(sym!("z"), term!(1.0)), (sym!("x"), y)]
+            .into_iter()
+            .collect();
+        assert_eq!(hash_bindings(&one), hash_bindings(&two));
+
+        let three: Bindings = vec![(sym!("z"), term!(2))].into_iter().collect();
+        assert_ne!(hash_bindings(&one), hash_bindings(&three));
+    }
+
     #[test]
     fn bind() {
         let x = sym!("x");
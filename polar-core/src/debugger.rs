@@ -1,3 +1,4 @@
+use std::collections::{BTreeSet, HashSet};
 use std::rc::Rc;
 
 use super::bindings::Binding;
@@ -20,6 +21,33 @@ impl PolarVirtualMachine {
         format!("QUERY: {}, BINDINGS: {{{}}}", query, bindings_str)
     }
 
+    /// Bindings for the variables of the innermost rule being evaluated,
+    /// named as they appear in the rule's source.
+    pub fn rule_bindings(&self) -> Vec<Binding> {
+        let rule = self.trace_stack.iter().rev().find_map(|frame| {
+            frame.last().and_then(|trace| match &trace.node {
+                Node::Rule(rule) => Some(rule.clone()),
+                _ => None,
+            })
+        });
+        let mut variables = HashSet::new();
+        if let Some(rule) = rule {
+            for param in &rule.params {
+                param.parameter.variables(&mut variables);
+            }
+            rule.body.variables(&mut variables);
+        }
+        let mut variables = variables
+            .into_iter()
+            .filter(|v| !v.is_temporary_var())
+            .collect::<Vec<_>>();
+        variables.sort();
+        variables
+            .iter()
+            .map(|v| get_binding_for_var(&v.0, self))
+            .collect()
+    }
+
     /// If the inner [`Debugger`](struct.Debugger.html) returns a [`Goal`](../vm/enum.Goal.html),
     /// push it onto the goal stack.
     pub fn maybe_break(&mut self, event: DebugEvent) -> PolarResult<bool> {
@@ -81,6 +109,9 @@ pub struct Debugger {
     ///   [`maybe_break`](struct.Debugger.html#method.maybe_break).
     step: Option<Step>,
     last: Option<String>,
+    /// Names of the rules to pause on whenever one of them is applied,
+    /// regardless of [`step`](struct.Debugger.html#structfield.step).
+    breakpoints: BTreeSet<String>,
 }
 
 impl Debugger {
@@ -108,6 +139,9 @@ impl Debugger {
     /// - `Some(Goal::Debug { message })` -> Pause evaluation.
     /// - `None` -> Continue evaluation.
     fn maybe_break(&self, event: DebugEvent, vm: &PolarVirtualMachine) -> Option<Goal> {
+        if matches!(event, DebugEvent::Rule) && self.at_breakpoint(vm) {
+            return self.break_query(vm);
+        }
         self.step.as_ref().and_then(|step| match (step, event) {
             (Step::Goal, DebugEvent::Goal(goal)) => Some(Goal::Debug {
                 message: goal.to_string(),
@@ -134,6 +168,30 @@ impl Debugger {
         })
     }
 
+    /// Whether the rule that was just applied has a breakpoint on it.
+    fn at_breakpoint(&self, vm: &PolarVirtualMachine) -> bool {
+        matches!(
+            vm.trace.last().map(|trace| &trace.node),
+            Some(Node::Rule(rule)) if self.breakpoints.contains(&rule.name.0)
+        )
+    }
+
+    fn show_breakpoints(&self) -> Goal {
+        let message = if self.breakpoints.is_empty() {
+            "No breakpoints set.".to_string()
+        } else {
+            format!(
+                "Breakpoints: {}",
+                self.breakpoints
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        Goal::Debug { message }
+    }
+
     pub fn break_msg(&self, vm: &PolarVirtualMachine) -> Option<String> {
         vm.trace.last().and_then(|trace| match trace.node {
             Node::Term(ref q) => match q.value() {
@@ -208,6 +266,20 @@ impl Debugger {
             "r" | "rule" => {
                 self.step = Some(Step::Rule)
             }
+            "b" | "break" => {
+                self.breakpoints.extend(parts[1..].iter().map(|name| name.to_string()));
+                return Some(self.show_breakpoints());
+            }
+            "clear" => {
+                if parts.len() > 1 {
+                    for name in &parts[1..] {
+                        self.breakpoints.remove(*name);
+                    }
+                } else {
+                    self.breakpoints.clear();
+                }
+                return Some(self.show_breakpoints());
+            }
             "l" | "line" => {
                 let lines = parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
                 return Some(Goal::Debug {
@@ -289,6 +361,10 @@ impl Debugger {
   g[oal]                  Step to the next goal of the Polar VM.
   e[rror]                 Step to the next error.
   r[ule]                  Step to the next rule.
+  b[reak] [<name> ...]    Pause whenever a rule with one of the given names is
+                          applied, and print the rules with breakpoints.
+  clear [<name> ...]      Remove the breakpoints on the given rules, or all
+                          breakpoints if no names are given.
   l[ine] [<n>]            Print the current line and <n> lines of context.
   query [<i>]             Print the current query or the query at level <i> in the query stack.
   stack | trace           Print the current query stack.
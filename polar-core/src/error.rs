@@ -64,6 +64,23 @@ impl PolarError {
         }
     }
 
+    /// Whether the error came from setting up the knowledge base (parsing,
+    /// validating or loading a policy, or registering constants) rather than
+    /// from running a query. Hosts typically treat the former as fatal at
+    /// startup and the latter as a failure of a single query.
+    ///
+    /// Parse errors always count as load errors, including those raised
+    /// while parsing a query string.
+    pub fn is_load_error(&self) -> bool {
+        use ErrorKind::*;
+        use RuntimeError::*;
+        match &self.0 {
+            Parse(_) | Validation(_) => true,
+            Runtime(MultipleLoadError | InvalidRegistration { .. }) => true,
+            Runtime(_) | Operational(_) => false,
+        }
+    }
+
     pub fn get_context(&self) -> Option<Context> {
         use ErrorKind::*;
         use OperationalError::*;
@@ -100,6 +117,7 @@ impl PolarError {
 
                 // These errors track `term`, from which we derive the context.
                 ArithmeticError { term }
+                | AssertionFailed { term }
                 | TypeError { term, .. }
                 | UnhandledPartial { term, .. }
                 | Unsupported { term, .. } => term.parsed_context().cloned(),
@@ -107,11 +125,14 @@ impl PolarError {
                 // These errors never have context.
                 StackOverflow { .. }
                 | QueryTimeout { .. }
+                | TermSizeExceeded { .. }
+                | TooManyApplicableRules { .. }
                 | IncompatibleBindings { .. }
                 | DataFilteringFieldMissing { .. }
                 | DataFilteringUnsupportedOp { .. }
                 | InvalidRegistration { .. }
                 | QueryForUndefinedRule { .. }
+                | QueryForDisallowedRule { .. }
                 | MultipleLoadError => None,
             },
 
@@ -345,6 +366,17 @@ pub enum RuntimeError {
         elapsed: u64,
         timeout: u64,
     },
+    /// A unified term had more nodes than the query's `max_term_size`.
+    TermSizeExceeded {
+        max: usize,
+    },
+    /// A call to the rule `name` applied to more rules than the query's
+    /// `max_applicable_rules`.
+    TooManyApplicableRules {
+        name: String,
+        count: usize,
+        max: usize,
+    },
     Application {
         msg: String,
         stack_trace: String,
@@ -377,6 +409,15 @@ pub enum RuntimeError {
     QueryForUndefinedRule {
         name: String,
     },
+    /// The query called a rule outside of its allowlist.
+    QueryForDisallowedRule {
+        name: String,
+    },
+    /// The argument of an `assert` had no solutions.
+    AssertionFailed {
+        /// The asserted term, tracked for lexical context.
+        term: Term,
+    },
 }
 
 impl From<RuntimeError> for PolarError {
@@ -400,6 +441,14 @@ impl fmt::Display for RuntimeError {
                 write!(f, "{}", msg)
             }
             Self::QueryTimeout { elapsed, timeout } => write!(f, "Query timeout: Query running for {}ms, which exceeds the timeout of {}ms. To disable timeouts, set the POLAR_TIMEOUT_MS environment variable to 0.", elapsed, timeout),
+            Self::TermSizeExceeded { max } => {
+                write!(f, "Term size exceeded! MAX_TERM_SIZE = {}", max)
+            }
+            Self::TooManyApplicableRules { name, count, max } => write!(
+                f,
+                "Too many applicable rules for {}: {} rules apply, but MAX_APPLICABLE_RULES = {}",
+                name, count, max
+            ),
             Self::Application {
                 msg, stack_trace, ..
             } => {
@@ -471,6 +520,10 @@ The expression is: {expr}
             }
             Self::MultipleLoadError => write!(f, "Cannot load additional Polar code -- all Polar code must be loaded at the same time."),
             Self::QueryForUndefinedRule { name } => write!(f, "Query for undefined rule `{}`", name),
+            Self::QueryForDisallowedRule { name } => {
+                write!(f, "Query for rule `{}`, which is not allowed", name)
+            }
+            Self::AssertionFailed { term } => write!(f, "Assertion failed: {}", term),
         }
     }
 }
@@ -91,6 +91,14 @@ pub enum QueryEvent {
         args: TermList,
     },
 
+    /// Checks whether the host operator `name` holds for `args`. Answered
+    /// with `question_result`.
+    ExternalOperator {
+        call_id: u64,
+        name: Symbol,
+        args: TermList,
+    },
+
     NextExternal {
         call_id: u64,
         iterable: Term,
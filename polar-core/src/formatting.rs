@@ -117,6 +117,7 @@ fn precedence(o: &Operator) -> i32 {
         Operator::New => 10,
         Operator::Cut => 10,
         Operator::ForAll => 10,
+        Operator::Assert => 10,
         Operator::Dot => 9,
         Operator::In => 8,
         Operator::Isa => 8,
@@ -448,6 +449,7 @@ mod to_polar {
                 In => "in",
                 Cut => "cut",
                 ForAll => "forall",
+                Assert => "assert",
                 Debug => "debug",
                 Print => "print",
                 Isa => "matches",
@@ -470,6 +472,7 @@ mod to_polar {
                     self.args[0].to_polar(),
                     self.args[1].to_polar()
                 ),
+                Assert => format!("assert({})", self.args[0].to_polar()),
                 New => {
                     if self.args.len() == 1 {
                         format!("new {}", to_polar_parens(self.operator, &self.args[0]))
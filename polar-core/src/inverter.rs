@@ -220,6 +220,14 @@ impl Runnable for Inverter {
                     return Ok(QueryEvent::Done { result });
                 }
                 QueryEvent::Result { .. } => {
+                    // Only variables that were partially bound before the
+                    // inversion can have constraints passed out. If there
+                    // are none, the first result decides the inversion, so
+                    // stop without looking for more.
+                    if self.results.is_empty() && !self.vm.has_partials_at_point(&self.bsp) {
+                        return Ok(QueryEvent::Done { result: false });
+                    }
+
                     // Retrieve new bindings made when running inverted query.
                     let binding_follower = self
                         .vm
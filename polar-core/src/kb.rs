@@ -1,13 +1,15 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-pub use super::bindings::Bindings;
+pub use super::bindings::{hash_bindings, Bindings};
 use super::constants::Constants;
 use super::counter::Counter;
 use super::diagnostic::Diagnostic;
 use super::error::{invalid_state, PolarError, PolarResult, RuntimeError, ValidationError};
+use super::lexer::reserved_word;
 use super::resource_block::{ResourceBlocks, ACTOR_UNION_NAME, RESOURCE_UNION_NAME};
 use super::rules::*;
+use super::sources::SourceInfo;
 use super::terms::*;
 use super::validations::check_undefined_rule_calls;
 
@@ -34,7 +36,9 @@ pub struct KnowledgeBase {
     /// Map from contents to filename for files loaded into the KB.
     loaded_content: HashMap<String, String>,
 
-    rules: HashMap<Symbol, GenericRule>,
+    /// Shared with running queries, which keep the rules they started with
+    /// even if the knowledge base is reloaded. See [`KnowledgeBase::rules_snapshot`].
+    rules: Arc<HashMap<Symbol, GenericRule>>,
     rule_types: RuleTypes,
     /// For symbols returned from gensym.
     gensym_counter: Counter,
@@ -44,6 +48,12 @@ pub struct KnowledgeBase {
 
     /// Resource block bookkeeping.
     pub resource_blocks: ResourceBlocks,
+
+    /// Drop rules identical to one already loaded instead of adding them again.
+    dedup_rules: bool,
+
+    /// Names of the binary operators implemented by the host.
+    operators: HashSet<Symbol>,
 }
 
 impl KnowledgeBase {
@@ -80,17 +90,69 @@ impl KnowledgeBase {
     /// Add a generic rule to the knowledge base.
     #[cfg(test)]
     pub fn add_generic_rule(&mut self, rule: GenericRule) {
-        self.rules.insert(rule.name.clone(), rule);
+        Arc::make_mut(&mut self.rules).insert(rule.name.clone(), rule);
     }
 
     pub fn add_rule(&mut self, rule: Rule) {
-        let generic_rule = self
-            .rules
+        let generic_rule = Arc::make_mut(&mut self.rules)
             .entry(rule.name.clone())
             .or_insert_with(|| GenericRule::new(rule.name.clone(), vec![]));
+        if self.dedup_rules && generic_rule.rules.values().any(|r| **r == rule) {
+            return;
+        }
         generic_rule.add_rule(Arc::new(rule));
     }
 
+    /// Skip loading a rule when an identical rule (same head and body) is
+    /// already defined, so that duplicated definitions don't yield duplicate
+    /// results. Comparison happens after rewriting, so rules whose bodies
+    /// introduce temporaries (e.g., attribute lookups) are never considered
+    /// identical.
+    pub fn set_dedup_rules(&mut self, dedup: bool) {
+        self.dedup_rules = dedup;
+    }
+
+    /// Add a fact `name(args...)` for each row of `args`, bypassing the parser
+    /// and rewriter. Each row is stored as a rule with an empty body, in the
+    /// same argument index as facts loaded from a policy. The rows are checked
+    /// against any rule types for `name`, and none are added if one fails.
+    pub fn load_facts<I>(&mut self, name: Symbol, rows: I) -> PolarResult<()>
+    where
+        I: IntoIterator<Item = Vec<Term>>,
+    {
+        let facts = rows
+            .into_iter()
+            .map(|args| {
+                let params = args
+                    .into_iter()
+                    .map(|parameter| Parameter {
+                        parameter,
+                        specializer: None,
+                    })
+                    .collect();
+                Rule {
+                    name: name.clone(),
+                    params,
+                    body: Term::new_from_ffi(Value::Expression(Operation {
+                        operator: Operator::And,
+                        args: vec![],
+                    })),
+                    source_info: SourceInfo::Ffi,
+                    required: false,
+                }
+            })
+            .collect::<Vec<_>>();
+        if let Some(types) = self.rule_types.get(&name) {
+            for fact in &facts {
+                self.check_rule_types(fact, types)?;
+            }
+        }
+        for fact in facts {
+            self.add_rule(fact);
+        }
+        Ok(())
+    }
+
     pub fn validate_rules(&self) -> Vec<Diagnostic> {
         // Prior to #1310 these validations were not order dependent due to the
         // use of static default rule types.
@@ -112,33 +174,11 @@ impl KnowledgeBase {
     /// Validate that all rules loaded into the knowledge base are valid based on rule types.
     fn validate_rule_types(&self) -> PolarResult<()> {
         // For every rule, if there *is* a rule type, check that the rule matches the rule type.
-        for (rule_name, generic_rule) in &self.rules {
+        for (rule_name, generic_rule) in self.rules.iter() {
             if let Some(types) = self.rule_types.get(rule_name) {
                 // If a type with the same name exists, then the parameters must match for each rule
                 for rule in generic_rule.rules.values() {
-                    let mut msg = "Must match one of the following rule types:\n".to_owned();
-
-                    let results = types
-                        .iter()
-                        .map(|rule_type| {
-                            self.rule_params_match(rule.as_ref(), rule_type)
-                                .map(|result| (result, rule_type))
-                        })
-                        .collect::<PolarResult<Vec<_>>>()?;
-                    let found_match = results.iter().any(|(result, rule_type)| match result {
-                        RuleParamMatch::True => true,
-                        RuleParamMatch::False(message) => {
-                            msg.push_str(&format!(
-                                "\n{}\n\tFailed to match because: {}\n",
-                                rule_type, message
-                            ));
-                            false
-                        }
-                    });
-                    if !found_match {
-                        let rule = Rule::clone(rule);
-                        return Err(ValidationError::InvalidRule { rule, msg }.into());
-                    }
+                    self.check_rule_types(rule, types)?;
                 }
             }
         }
@@ -169,6 +209,34 @@ impl KnowledgeBase {
         Ok(())
     }
 
+    /// Check that `rule` matches at least one of `types`.
+    fn check_rule_types(&self, rule: &Rule, types: &[Rule]) -> PolarResult<()> {
+        let mut msg = "Must match one of the following rule types:\n".to_owned();
+
+        let results = types
+            .iter()
+            .map(|rule_type| {
+                self.rule_params_match(rule, rule_type)
+                    .map(|result| (result, rule_type))
+            })
+            .collect::<PolarResult<Vec<_>>>()?;
+        let found_match = results.iter().any(|(result, rule_type)| match result {
+            RuleParamMatch::True => true,
+            RuleParamMatch::False(message) => {
+                msg.push_str(&format!(
+                    "\n{}\n\tFailed to match because: {}\n",
+                    rule_type, message
+                ));
+                false
+            }
+        });
+        if !found_match {
+            let rule = rule.clone();
+            return Err(ValidationError::InvalidRule { rule, msg }.into());
+        }
+        Ok(())
+    }
+
     /// Determine whether the fields of a rule parameter specializer match the fields of a type parameter specializer.
     /// Rule fields match if they are a superset of type fields and all field values are equal.
     // TODO: once field-level specializers are working this should be updated so
@@ -550,6 +618,26 @@ impl KnowledgeBase {
         &self.rules
     }
 
+    /// The rules as they are now. Later changes to the knowledge base copy
+    /// the rules rather than change the snapshot.
+    pub fn rules_snapshot(&self) -> Arc<HashMap<Symbol, GenericRule>> {
+        self.rules.clone()
+    }
+
+    /// Signatures of all loaded rules, ordered by name and then load order.
+    pub fn rule_signatures(&self) -> Vec<RuleSignature> {
+        let mut generic_rules = self.rules.values().collect::<Vec<_>>();
+        generic_rules.sort_by(|a, b| a.name.0.cmp(&b.name.0));
+        generic_rules
+            .into_iter()
+            .flat_map(|generic_rule| {
+                let mut rules = generic_rule.rules.iter().collect::<Vec<_>>();
+                rules.sort_by_key(|(id, _)| **id);
+                rules.into_iter().map(|(_, rule)| rule.as_ref().into())
+            })
+            .collect()
+    }
+
     #[cfg(test)]
     pub fn get_rule_types(&self, name: &Symbol) -> Option<&Vec<Rule>> {
         self.rule_types.get(name)
@@ -598,6 +686,31 @@ impl KnowledgeBase {
         Ok(())
     }
 
+    /// Define a binary operator implemented by the host. Operators are called
+    /// like rules, `name(left, right)`. A call to `name` that no rule named
+    /// `name` is defined for asks the host with an `ExternalOperator` event,
+    /// and succeeds if the host answers `true`.
+    ///
+    /// Built-in operators and keywords (`in`, `matches`, `print`, etc.) are
+    /// handled by the parser before any rule or operator is considered, so
+    /// their names can't be registered.
+    pub fn register_operator(&mut self, name: Symbol) -> PolarResult<()> {
+        let msg = if reserved_word(&name.0).is_some() {
+            format!("'{}' is a built-in operator or keyword.", name)
+        } else if self.operators.contains(&name) {
+            "an operator with that name is already registered.".to_owned()
+        } else {
+            self.operators.insert(name);
+            return Ok(());
+        };
+        Err(RuntimeError::InvalidRegistration { msg, sym: name }.into())
+    }
+
+    /// Getter for `operators` set without exposing it for mutation.
+    pub fn get_operators(&self) -> &HashSet<Symbol> {
+        &self.operators
+    }
+
     /// Return true if a constant with the given name has been defined.
     pub fn is_constant(&self, name: &Symbol) -> bool {
         self.constants.contains_key(name)
@@ -639,8 +752,32 @@ impl KnowledgeBase {
         Ok(())
     }
 
+    /// An empty knowledge base with the same registered constants, classes
+    /// and operators as this one, to load a new policy into before replacing
+    /// this one's with it. See [`KnowledgeBase::replace_policy`].
+    pub fn staging(&self) -> Self {
+        Self {
+            constants: self.constants.clone(),
+            mro: self.mro.clone(),
+            gensym_counter: self.gensym_counter.clone(),
+            id_counter: self.id_counter.clone(),
+            dedup_rules: self.dedup_rules,
+            operators: self.operators.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Replace the loaded policy with the one loaded into `staged`.
+    pub fn replace_policy(&mut self, staged: Self) {
+        self.rules = staged.rules;
+        self.rule_types = staged.rule_types;
+        self.inline_queries = staged.inline_queries;
+        self.loaded_content = staged.loaded_content;
+        self.resource_blocks = staged.resource_blocks;
+    }
+
     pub fn clear_rules(&mut self) {
-        self.rules.clear();
+        self.rules = Arc::default();
         self.rule_types.reset();
         self.inline_queries.clear();
         self.loaded_content.clear();
@@ -832,7 +969,7 @@ impl KnowledgeBase {
     }
 
     pub fn is_union(&self, maybe_union: &Term) -> bool {
-        (maybe_union.is_actor_union()) || (maybe_union.is_resource_union())
+        maybe_union.is_union()
     }
 
     pub fn get_union_members(&self, union: &Term) -> &HashSet<Term> {
@@ -1579,4 +1716,46 @@ mod tests {
         let expected = "Rule specializer Class1 on parameter 1 must match rule type specializer ExternalInstanceWithoutMRO1";
         assert!(diagnostic.contains(expected), "{}", diagnostic);
     }
+
+    #[test]
+    fn test_rule_signatures() {
+        use crate::{polar::Polar, sources::Source};
+
+        let polar = Polar::new();
+        let src = "g(x, y) if f(x) and f(y);\nf(_: {id: 1});\nf(1);\n";
+        polar
+            .load(vec![Source::new_with_name("test.polar", src)])
+            .unwrap();
+
+        let signatures = polar.rule_signatures();
+        let summary = signatures
+            .iter()
+            .map(|s| {
+                let specializers = s
+                    .specializers
+                    .iter()
+                    .map(|s| s.as_ref().map(ToString::to_string))
+                    .collect::<Vec<_>>();
+                (s.name.0.as_str(), s.arity, specializers)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("f", 1, vec![Some("{id: 1}".to_owned())]),
+                ("f", 1, vec![None]),
+                ("g", 2, vec![None, None]),
+            ]
+        );
+
+        let spans = signatures
+            .iter()
+            .map(|s| {
+                assert_eq!(s.filename.as_deref(), Some("test.polar"));
+                let (left, right) = s.span.unwrap();
+                &src[left..right]
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(spans, vec!["f(_: {id: 1})", "f(1)", "g(x, y)"]);
+    }
 }
@@ -28,6 +28,34 @@ pub fn loc_to_pos(src: &str, loc: usize) -> SrcPos {
     (row, col)
 }
 
+// The token for a word with built-in meaning, if `word` is one.
+pub fn reserved_word(word: &str) -> Option<Token> {
+    let token = match word {
+        "true" => Token::Boolean(true),
+        "false" => Token::Boolean(false),
+        "inf" => Token::Float(f64::INFINITY),
+        "nan" => Token::Float(f64::NAN),
+        "new" => Token::New,
+        "in" => Token::In,
+        "cut" => Token::Cut,
+        "debug" => Token::Debug,
+        "print" => Token::Print,
+        "isa" => Token::Isa,
+        "forall" => Token::ForAll,
+        "assert" => Token::Assert,
+        "if" => Token::If,
+        "and" => Token::And,
+        "or" => Token::Or,
+        "not" => Token::Not,
+        "matches" => Token::Matches,
+        "type" => Token::Type,
+        "mod" => Token::Mod,
+        "rem" => Token::Rem,
+        _ => return None,
+    };
+    Some(token)
+}
+
 pub struct Lexer<'input> {
     c: Option<(usize, char)>,
     chars: Peekable<CharIndices<'input>>,
@@ -86,6 +114,7 @@ pub enum Token {
     Print,     // print()
     Isa,       // isa
     ForAll,    // forall
+    Assert,    // assert()
     If,        // if
     And,       // and
     Or,        // or
@@ -136,6 +165,7 @@ impl ToString for Token {
             Token::Print => "print".to_owned(),     // print
             Token::Isa => "isa".to_owned(),         // isa
             Token::ForAll => "forall".to_owned(),   // forall
+            Token::Assert => "assert".to_owned(),   // assert
             Token::If => "if".to_owned(),           // if
             Token::And => "and".to_owned(),         // and
             Token::Or => "or".to_owned(),           // or
@@ -221,28 +251,8 @@ impl<'input> Lexer<'input> {
             }
         }
 
-        let token = match self.buf.as_ref() {
-            "true" => Token::Boolean(true),
-            "false" => Token::Boolean(false),
-            "inf" => Token::Float(f64::INFINITY),
-            "nan" => Token::Float(f64::NAN),
-            "new" => Token::New,
-            "in" => Token::In,
-            "cut" => Token::Cut,
-            "debug" => Token::Debug,
-            "print" => Token::Print,
-            "isa" => Token::Isa,
-            "forall" => Token::ForAll,
-            "if" => Token::If,
-            "and" => Token::And,
-            "or" => Token::Or,
-            "not" => Token::Not,
-            "matches" => Token::Matches,
-            "type" => Token::Type,
-            "mod" => Token::Mod,
-            "rem" => Token::Rem,
-            _ => Token::Symbol(Symbol::new(&self.buf)),
-        };
+        let token =
+            reserved_word(&self.buf).unwrap_or_else(|| Token::Symbol(Symbol::new(&self.buf)));
         Some(Ok((start, token, last + 1)))
     }
 
@@ -283,6 +283,11 @@ mod tests {
         assert_eq!(term.to_string(), "{} matches {}");
         let term = parse_term("{x: 1} matches {}");
         assert_eq!(term.to_string(), "{x: 1} matches {}");
+        let term = parse_term("x matches (A or B{y: 1} or {})");
+        assert_eq!(
+            term.to_string(),
+            "x matches (A{} or B{y: 1} or {})"
+        );
     }
 
     #[test]
@@ -319,6 +324,9 @@ mod tests {
             parse_term(q),
             term!(op!(Dot, term!(sym!("x")), term!("invalid-key"))),
         );
+
+        let q = "x.(-1)";
+        assert_eq!(parse_term(q), term!(op!(Dot, term!(sym!("x")), term!(-1))));
     }
 
     #[test]
@@ -10,6 +10,7 @@ use super::parser;
 use super::query::Query;
 use super::resource_block::resource_block_from_productions;
 use super::rewrites::*;
+use super::rules::{Rule, RuleSignature};
 use super::sources::*;
 use super::terms::*;
 use super::validations::{
@@ -46,6 +47,11 @@ impl Polar {
 
     /// Load `sources` into the KB, returning compile-time diagnostics accumulated during the load.
     pub fn diagnostic_load(&self, sources: Vec<Source>) -> Vec<Diagnostic> {
+        let mut kb = self.kb.write().unwrap();
+        self.load_into(&mut kb, sources)
+    }
+
+    fn load_into(&self, kb: &mut KnowledgeBase, sources: Vec<Source>) -> Vec<Diagnostic> {
         // Separate function so that errors returned with `?` are captured.
         fn load_source(source: Source, kb: &mut KnowledgeBase) -> PolarResult<Vec<Diagnostic>> {
             if let Some(ref filename) = source.filename {
@@ -104,11 +110,10 @@ impl Polar {
             Ok(diagnostics)
         }
 
-        let mut kb = self.kb.write().unwrap();
         let mut diagnostics = vec![];
 
         for source in sources {
-            match load_source(source, &mut kb) {
+            match load_source(source, kb) {
                 Ok(mut ds) => diagnostics.append(&mut ds),
                 Err(e) => diagnostics.push(Diagnostic::Error(e)),
             }
@@ -135,6 +140,14 @@ impl Polar {
             return diagnostics;
         }
 
+        diagnostics.append(&mut self.validate_policy(kb));
+        diagnostics
+    }
+
+    /// Checks run against the whole policy once all of it has been loaded.
+    fn validate_policy(&self, kb: &mut KnowledgeBase) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+
         // Generate appropriate rule_type definitions using the types contained in policy resource
         // blocks.
         if let Err(e) = kb.create_resource_specific_rule_types() {
@@ -146,13 +159,13 @@ impl Polar {
 
         // Perform validation checks against the whole policy
         if !self.ignore_no_allow_warning {
-            if let Some(w) = check_no_allow_rule(&kb) {
+            if let Some(w) = check_no_allow_rule(kb) {
                 diagnostics.push(w)
             }
         }
 
         // Check for has_permission calls alongside resource block definitions
-        if let Some(w) = check_resource_blocks_missing_has_permission(&kb) {
+        if let Some(w) = check_resource_blocks_missing_has_permission(kb) {
             diagnostics.push(Diagnostic::Warning(w.into()))
         };
 
@@ -161,14 +174,31 @@ impl Polar {
 
     /// Load `Source`s into the KB.
     pub fn load(&self, sources: Vec<Source>) -> PolarResult<()> {
-        if let Ok(kb) = self.kb.read() {
-            if kb.has_rules() {
-                return Err(RuntimeError::MultipleLoadError.into());
-            }
+        let mut kb = self.kb.write().unwrap();
+        if kb.has_rules() {
+            return Err(RuntimeError::MultipleLoadError.into());
         }
+        let diagnostics = self.load_into(&mut kb, sources);
+        self.finish_load(&mut kb, diagnostics)
+    }
+
+    /// Replace the rules in the KB with those in `sources`. The new rules
+    /// are loaded into a staging KB and swapped in under a single write lock,
+    /// so new queries see either the old policy or the new one, never an
+    /// empty or half-loaded KB. Queries that are already running keep the
+    /// rules they started with. If loading fails, the old policy is kept.
+    pub fn reload(&self, sources: Vec<Source>) -> PolarResult<()> {
+        let mut kb = self.kb.write().unwrap();
+        let mut staged = kb.staging();
+        let diagnostics = self.load_into(&mut staged, sources);
+        self.finish_load(&mut staged, diagnostics)?;
+        kb.replace_policy(staged);
+        Ok(())
+    }
 
+    fn finish_load(&self, kb: &mut KnowledgeBase, diagnostics: Vec<Diagnostic>) -> PolarResult<()> {
         let (mut errors, mut warnings) = (vec![], vec![]);
-        for diagnostic in self.diagnostic_load(sources) {
+        for diagnostic in diagnostics {
             match diagnostic {
                 Diagnostic::Error(e) => errors.push(e),
                 Diagnostic::Warning(w) => warnings.push(w),
@@ -180,7 +210,7 @@ impl Polar {
 
         if let Some(e) = errors.into_iter().next() {
             // If we've encountered any errors, clear the KB.
-            self.clear_rules();
+            kb.clear_rules();
             return Err(e);
         }
         Ok(())
@@ -191,6 +221,25 @@ impl Polar {
         self.load(vec![Source::new(src)])
     }
 
+    /// Add many ground facts for the rule `name` at once, without parsing them.
+    /// Intended for hosts that sync tables of data into the knowledge base;
+    /// call it after the policy has been loaded. See
+    /// [`KnowledgeBase::load_facts`].
+    pub fn load_facts<I>(&self, name: &str, rows: I) -> PolarResult<()>
+    where
+        I: IntoIterator<Item = Vec<Term>>,
+    {
+        let mut kb = self.kb.write().unwrap();
+        kb.load_facts(Symbol::new(name), rows)
+    }
+
+    /// Ignore rules that duplicate an already loaded rule. Applies to rules
+    /// loaded after the call.
+    pub fn set_dedup_rules(&self, dedup: bool) {
+        let mut kb = self.kb.write().unwrap();
+        kb.set_dedup_rules(dedup);
+    }
+
     /// Clear rules from the knowledge base
     pub fn clear_rules(&self) {
         let mut kb = self.kb.write().unwrap();
@@ -207,18 +256,94 @@ impl Polar {
     }
 
     pub fn new_query_from_term(&self, mut term: Term, trace: bool) -> Query {
-        use crate::vm::{Goal, PolarVirtualMachine};
         {
             let mut kb = self.kb.write().unwrap();
             term = rewrite_term(term, &mut kb);
         }
+        self.new_query_from_rewritten_term(term, trace)
+    }
+
+    /// Create a query for the rule `name` called with `args`. Arguments can be
+    /// anything that converts into a term, e.g. integers, strings, booleans,
+    /// or vectors of those.
+    pub fn query_rule<I>(&self, name: &str, args: I, trace: bool) -> Query
+    where
+        I: IntoIterator,
+        I::Item: Into<Term>,
+    {
+        let call = Call {
+            name: Symbol::new(name),
+            args: args.into_iter().map(Into::into).collect(),
+            kwargs: None,
+        };
+        self.new_query_from_term(Term::new_from_ffi(Value::Call(call)), trace)
+    }
+
+    /// Create a query for each of `terms`, rewriting them all under a single
+    /// lock on the knowledge base. Useful for hosts that check many argument
+    /// tuples against the same rule at once.
+    pub fn new_queries_from_terms(&self, terms: Vec<Term>, trace: bool) -> Vec<Query> {
+        let terms = {
+            let mut kb = self.kb.write().unwrap();
+            terms
+                .into_iter()
+                .map(|term| rewrite_term(term, &mut kb))
+                .collect::<Vec<_>>()
+        };
+        terms
+            .into_iter()
+            .map(|term| self.new_query_from_rewritten_term(term, trace))
+            .collect()
+    }
+
+    fn new_query_from_rewritten_term(&self, term: Term, trace: bool) -> Query {
+        use crate::vm::{Goal, PolarVirtualMachine};
         let query = Goal::Query { term: term.clone() };
         let vm =
             PolarVirtualMachine::new(self.kb.clone(), trace, vec![query], self.messages.clone());
         Query::new(vm, term)
     }
 
-    // @TODO: Direct load_rules endpoint.
+    /// The rules in the knowledge base as they were compiled at load time,
+    /// ordered by name and then load order. Rules and terms serialize with
+    /// serde, so a host can persist these and restore them with
+    /// [`Polar::load_rules`] instead of parsing the policy again.
+    pub fn rules(&self) -> Vec<Rule> {
+        let kb = self.kb.read().unwrap();
+        let mut generic_rules = kb.get_rules().values().collect::<Vec<_>>();
+        generic_rules.sort_by(|a, b| a.name.cmp(&b.name));
+        generic_rules
+            .into_iter()
+            .flat_map(|generic_rule| {
+                let mut rules = generic_rule.rules.iter().collect::<Vec<_>>();
+                rules.sort_by_key(|(id, _)| **id);
+                rules.into_iter().map(|(_, rule)| rule.as_ref().clone())
+            })
+            .collect()
+    }
+
+    /// Signatures of all loaded rules, ordered by name and then load order,
+    /// for editor tooling.
+    pub fn rule_signatures(&self) -> Vec<RuleSignature> {
+        self.kb.read().unwrap().rule_signatures()
+    }
+
+    /// Load rules previously exported with [`Polar::rules`]. They are already
+    /// rewritten, so they are not parsed or rewritten again, but they are
+    /// validated as they would be by [`Polar::load`]. Rule types and resource
+    /// blocks are not exported, so rules are only checked against the
+    /// built-in rule types.
+    pub fn load_rules(&self, rules: Vec<Rule>) -> PolarResult<()> {
+        let mut kb = self.kb.write().unwrap();
+        if kb.has_rules() {
+            return Err(RuntimeError::MultipleLoadError.into());
+        }
+        for rule in rules {
+            kb.add_rule(rule);
+        }
+        let diagnostics = self.validate_policy(&mut kb);
+        self.finish_load(&mut kb, diagnostics)
+    }
 
     pub fn get_external_id(&self) -> u64 {
         self.kb.read().unwrap().new_id()
@@ -228,6 +353,13 @@ impl Polar {
         self.kb.write().unwrap().register_constant(name, value)
     }
 
+    /// Register a binary operator implemented by the host. See
+    /// [`KnowledgeBase::register_operator`].
+    pub fn register_operator(&self, name: Symbol) -> PolarResult<()> {
+        let mut kb = self.kb.write().unwrap();
+        kb.register_operator(name)
+    }
+
     /// Register MRO for `name` with `mro`.
     ///
     /// Params:
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use super::error::PolarResult;
 use super::events::*;
 use super::messages::*;
@@ -5,6 +7,18 @@ use super::runnable::Runnable;
 use super::terms::*;
 use super::vm::*;
 
+/// Counts of the work a query has done so far.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueryProgress {
+    /// Choice points created, e.g., for rules that might apply or for the
+    /// elements of a list iterated with `in`.
+    pub choice_points: usize,
+    /// Rule bodies entered.
+    pub rules_applied: usize,
+    /// Results produced.
+    pub results: usize,
+}
+
 pub struct Query {
     runnable_stack: Vec<(Box<dyn Runnable>, u64)>, // Tuple of Runnable + call_id.
     vm: PolarVirtualMachine,
@@ -22,11 +36,91 @@ impl Query {
         }
     }
 
-    #[cfg(target_arch = "wasm32")]
+    /// Override the log level for this query alone. `polar_log` takes the
+    /// same comma-separated values as the `POLAR_LOG` environment variable:
+    /// `off`, `info` (rules applied), `debug`, or `trace` (every goal,
+    /// query and unification), plus `now` to print to stderr immediately.
     pub fn set_logging_options(&mut self, rust_log: Option<String>, polar_log: Option<String>) {
         self.vm.set_logging_options(rust_log, polar_log);
     }
 
+    /// Raise `QueryTimeout` after `timeout_ms` (default `POLAR_TIMEOUT_MS` or 30s; 0 disables).
+    pub fn set_query_timeout_ms(&mut self, timeout_ms: u64) {
+        self.vm.set_query_timeout_ms(timeout_ms);
+    }
+
+    /// Raise `ArithmeticError` for NaN or infinite float results (default off).
+    pub fn set_strict_float_arithmetic(&mut self, strict: bool) {
+        self.vm.set_strict_float_arithmetic(strict);
+    }
+
+    /// Raise `Unsupported` for host method calls inside `not` or `forall` (default off).
+    pub fn set_forbid_calls_in_negation(&mut self, forbid: bool) {
+        self.vm.set_forbid_calls_in_negation(forbid);
+    }
+
+    /// Cap the goal and choice stacks (default `MAX_STACK_SIZE`) before raising `StackOverflow`.
+    pub fn set_stack_limit(&mut self, limit: usize) {
+        self.vm.set_stack_limit(limit);
+    }
+
+    /// Raise `TermSizeExceeded` when unifying a term with more nodes (default unlimited).
+    pub fn set_max_term_size(&mut self, max_term_size: Option<usize>) {
+        self.vm.set_max_term_size(max_term_size);
+    }
+
+    /// Raise `TooManyApplicableRules` when a call matches more rules (default unlimited).
+    pub fn set_max_applicable_rules(&mut self, max_applicable_rules: Option<usize>) {
+        self.vm.set_max_applicable_rules(max_applicable_rules);
+    }
+
+    /// Raise `QueryForDisallowedRule` when calling any other rule (default: all allowed).
+    pub fn set_allowed_rules(&mut self, allowed_rules: Option<HashSet<Symbol>>) {
+        self.vm.set_allowed_rules(allowed_rules);
+    }
+
+    /// Fail unifications that would create a cyclic term, e.g., `x = [x]` (default off).
+    pub fn set_occurs_check(&mut self, occurs_check: bool) {
+        self.vm.set_occurs_check(occurs_check);
+    }
+
+    /// Return unresolved bindings, including temporaries and constraints (default off).
+    pub fn set_raw_bindings(&mut self, raw: bool) {
+        self.vm.set_raw_bindings(raw);
+    }
+
+    /// Call `observer` with the innermost query each time a branch fails (default none).
+    pub fn set_backtrack_observer<F>(&mut self, observer: F)
+    where
+        F: Fn(&Term) + 'static,
+    {
+        self.vm.set_backtrack_observer(Some(std::rc::Rc::new(observer)));
+    }
+
+    /// Call `observer` with the query's progress every `every` choice points.
+    /// Counts include work done inside `not` and `forall`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is 0.
+    pub fn set_progress_observer<F>(&mut self, every: usize, observer: F)
+    where
+        F: Fn(&QueryProgress) + 'static,
+    {
+        assert!(every > 0, "progress interval must be non-zero");
+        self.vm
+            .set_progress_observer(Some((every, std::rc::Rc::new(observer))));
+    }
+
+    /// Call `observer` each time a candidate rule doesn't apply to a call (default none).
+    pub fn set_rule_mismatch_observer<F>(&mut self, observer: F)
+    where
+        F: Fn(&crate::rules::RuleMismatch) + 'static,
+    {
+        self.vm
+            .set_rule_mismatch_observer(Some(std::rc::Rc::new(observer)));
+    }
+
     /// Runnable lifecycle
     ///
     /// 1. Get Runnable A from the top of the Runnable stack, defaulting to the VM.
@@ -205,6 +205,50 @@ impl RuleIndex {
     }
 }
 
+/// Summary of a loaded rule, for editor tooling such as autocomplete and
+/// go-to-definition.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RuleSignature {
+    pub name: Symbol,
+    pub arity: usize,
+    /// Specializer of each parameter, if any.
+    pub specializers: Vec<Option<Term>>,
+    /// File the rule was loaded from, if it was loaded from a named source.
+    pub filename: Option<String>,
+    /// Start and end offsets of the rule head within its source. Use `loc_to_pos`
+    /// to convert to line and column.
+    pub span: Option<(usize, usize)>,
+}
+
+impl From<&Rule> for RuleSignature {
+    fn from(rule: &Rule) -> Self {
+        let context = rule.parsed_context();
+        Self {
+            name: rule.name.clone(),
+            arity: rule.params.len(),
+            specializers: rule.params.iter().map(|p| p.specializer.clone()).collect(),
+            filename: context.and_then(|c| c.source.filename.clone()),
+            span: context.map(|c| (c.left, c.right)),
+        }
+    }
+}
+
+/// Why a rule didn't apply to the arguments of a call.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuleMismatchReason {
+    /// The argument didn't unify with the parameter.
+    Unify { arg: Term, param: Term },
+    /// The argument didn't match the parameter's specializer.
+    Isa { arg: Term, specializer: Term },
+}
+
+/// A rule that was considered for a call but didn't apply to it.
+#[derive(Clone, Debug)]
+pub struct RuleMismatch {
+    pub rule: Arc<Rule>,
+    pub reason: RuleMismatchReason,
+}
+
 #[derive(Clone)]
 pub struct GenericRule {
     pub name: Symbol,
@@ -108,6 +108,7 @@ pub enum Operator {
     And,
     ForAll,
     Assign,
+    Assert,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
@@ -224,12 +225,33 @@ impl From<TermList> for Value {
     }
 }
 
+/// Lists of native values, e.g. for [`crate::polar::Polar::query_rule`].
+macro_rules! list_from_vec {
+    ($($t:ty),*) => {
+        $(
+            impl From<Vec<$t>> for Value {
+                fn from(other: Vec<$t>) -> Self {
+                    Self::List(other.into_iter().map(Term::from).collect())
+                }
+            }
+        )*
+    };
+}
+
+list_from_vec!(i64, f64, bool, String, &str);
+
 impl From<String> for Value {
     fn from(other: String) -> Self {
         Self::String(other)
     }
 }
 
+impl From<&str> for Value {
+    fn from(other: &str) -> Self {
+        Self::String(other.to_string())
+    }
+}
+
 impl From<ExternalInstance> for Value {
     fn from(other: ExternalInstance) -> Self {
         Self::ExternalInstance(other)
@@ -421,6 +443,21 @@ impl Term {
         walk_term(&mut VariableVisitor::new(vars), self);
     }
 
+    /// Get a set of the ids of all the external instances within a term.
+    pub fn instance_ids(&self, ids: &mut HashSet<u64>) {
+        struct InstanceIdVisitor<'set> {
+            ids: &'set mut HashSet<u64>,
+        }
+
+        impl<'set> Visitor for InstanceIdVisitor<'set> {
+            fn visit_instance_id(&mut self, i: &u64) {
+                self.ids.insert(*i);
+            }
+        }
+
+        walk_term(&mut InstanceIdVisitor { ids }, self);
+    }
+
     /// Does the given variable occur in this term?
     /// Should be much faster than accumulating the set and checking.
     pub fn contains_variable(&self, var: &Symbol) -> bool {
@@ -468,6 +505,10 @@ impl Term {
     pub fn is_resource_union(&self) -> bool {
         matches!(self.value(), Value::Pattern(Pattern::Instance(InstanceLiteral { tag, .. })) | Value::Variable(tag) if tag.0 == RESOURCE_UNION_NAME)
     }
+
+    pub fn is_union(&self) -> bool {
+        self.is_actor_union() || self.is_resource_union()
+    }
 }
 
 #[cfg(test)]
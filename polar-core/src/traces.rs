@@ -24,6 +24,18 @@ impl Trace {
             None
         }
     }
+
+    /// The rules that were applied to produce this trace, outermost first.
+    pub fn rules(&self) -> Vec<Arc<Rule>> {
+        let mut rules = vec![];
+        if let Node::Rule(r) = &self.node {
+            rules.push(r.clone());
+        }
+        for child in &self.children {
+            rules.append(&mut child.rules());
+        }
+        rules
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
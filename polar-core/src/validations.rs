@@ -217,7 +217,8 @@ impl<'kb> Visitor for UndefinedRuleCallVisitor<'kb> {
 }
 
 pub fn check_undefined_rule_calls(kb: &KnowledgeBase) -> Vec<Diagnostic> {
-    let mut visitor = UndefinedRuleCallVisitor::new(kb.get_rules().keys().collect());
+    let defined_rules = kb.get_rules().keys().chain(kb.get_operators()).collect();
+    let mut visitor = UndefinedRuleCallVisitor::new(defined_rules);
     for rule in kb.get_rules().values() {
         visitor.visit_generic_rule(rule);
     }
@@ -1,4 +1,4 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::BTreeMap;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
@@ -23,6 +23,7 @@ use crate::kb::*;
 use crate::messages::*;
 use crate::numerics::*;
 use crate::partial::{simplify_bindings_opt, simplify_partial, sub_this, IsaConstraintCheck};
+use crate::query::QueryProgress;
 use crate::rewrites::Renamer;
 use crate::rules::*;
 use crate::runnable::Runnable;
@@ -91,6 +92,11 @@ pub enum Goal {
         instance: Term,
         literal: InstanceLiteral,
     },
+    /// Ask the host whether the registered operator `name` holds for `args`.
+    CallOperator {
+        name: Symbol,
+        args: TermList,
+    },
     MakeExternal {
         constructor: Term,
         instance_id: u64,
@@ -143,6 +149,30 @@ pub enum Goal {
     AddConstraintsBatch {
         add_constraints: Rc<RefCell<Bindings>>,
     },
+
+    /// Note that the first `checks` applicability checks of a rule passed.
+    RuleChecksPassed {
+        checks: usize,
+    },
+
+    /// Report why `rule` didn't apply: the first of `reasons` whose check
+    /// wasn't noted as passed.
+    ReportRuleMismatch {
+        rule: Arc<Rule>,
+        reasons: Vec<RuleMismatchReason>,
+    },
+
+    /// Note that the condition of an `assert` produced a solution.
+    AssertionSucceeded {
+        succeeded: Rc<Cell<bool>>,
+    },
+
+    /// Runs once the condition of an `assert` has no more solutions: fail
+    /// if any were produced, otherwise raise `AssertionFailed`.
+    CheckAssertion {
+        succeeded: Rc<Cell<bool>>,
+        term: Term,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -153,6 +183,7 @@ pub struct Choice {
     queries: Queries,      // query stack snapshot
     trace: Vec<Rc<Trace>>, // trace snapshot
     trace_stack: TraceStack,
+    conditional: bool, // part of an if-then-else, not a branch of the query
 }
 
 pub type Choices = Vec<Choice>;
@@ -185,6 +216,15 @@ impl std::ops::DerefMut for GoalStack {
 
 pub type Queries = TermList;
 
+/// Called with the innermost query term whenever a branch fails.
+pub type BacktrackObserver = Rc<dyn Fn(&Term)>;
+
+/// Called with the progress of a query every so many choice points.
+pub type ProgressObserver = Rc<dyn Fn(&QueryProgress)>;
+
+/// Called whenever a rule is considered for a call but doesn't apply.
+pub type RuleMismatchObserver = Rc<dyn Fn(&RuleMismatch)>;
+
 pub fn compare(
     op: Operator,
     left: &Term,
@@ -242,11 +282,48 @@ pub struct PolarVirtualMachine {
     query_start_time: Option<std::time::Instant>,
     #[cfg(target_arch = "wasm32")]
     query_start_time: Option<f64>,
+    /// Wall-clock budget for the query in milliseconds, or 0 for none.
     query_timeout_ms: u64,
 
     /// Maximum size of goal stack
     stack_limit: usize,
 
+    /// Whether arithmetic producing NaN or ±∞ is an error.
+    strict_float_arithmetic: bool,
+
+    /// Whether calling external methods inside `not` or `forall` is an error.
+    forbid_calls_in_negation: bool,
+
+    /// Maximum number of nodes in a unified term, if any.
+    max_term_size: Option<usize>,
+
+    /// Maximum number of rules a single call may apply to, if any.
+    max_applicable_rules: Option<usize>,
+
+    /// Names of the only rules that may be called, if restricted.
+    allowed_rules: Option<HashSet<Symbol>>,
+
+    /// Whether to refuse bindings that would make a term contain itself.
+    occurs_check: bool,
+
+    /// Whether results contain the raw bindings instead of resolved values.
+    raw_bindings: bool,
+
+    /// Called whenever a branch fails, if set.
+    backtrack_observer: Option<BacktrackObserver>,
+
+    /// Work done so far, shared with the VMs for nested queries.
+    progress: Rc<Cell<QueryProgress>>,
+
+    /// Called with `progress` every so many choice points, if set.
+    progress_observer: Option<(usize, ProgressObserver)>,
+
+    /// Called whenever a rule doesn't apply to a call, if set.
+    rule_mismatch_observer: Option<RuleMismatchObserver>,
+
+    /// Applicability checks passed so far by the rule being filtered.
+    rule_checks_passed: usize,
+
     /// Binding stack constant below here.
     csp: Bsp,
 
@@ -256,6 +333,21 @@ pub struct PolarVirtualMachine {
     /// Rules and types.
     pub kb: Arc<RwLock<KnowledgeBase>>,
 
+    /// The rules in `kb` when the query started. Rules are looked up here
+    /// so that reloading the knowledge base doesn't affect a running query.
+    rules: Arc<HashMap<Symbol, GenericRule>>,
+
+    /// The host operators registered in `kb` when the query started, for the
+    /// same reason.
+    operators: HashSet<Symbol>,
+
+    /// The constants registered in `kb` when the query started.
+    constants: Arc<Bindings>,
+
+    /// The members of the `Actor` and `Resource` unions when the query started.
+    actors: Arc<HashSet<Term>>,
+    resources: Arc<HashSet<Term>>,
+
     /// Call ID -> result variable name table.
     call_id_symbols: HashMap<u64, Symbol>,
 
@@ -306,11 +398,16 @@ impl PolarVirtualMachine {
             .ok()
             .and_then(|timeout_str| timeout_str.parse::<u64>().ok())
             .unwrap_or(DEFAULT_TIMEOUT_MS);
-        let constants = kb
-            .read()
-            .expect("cannot acquire KB read lock")
-            .get_registered_constants()
-            .clone();
+        let (constants, rules, operators, actors, resources) = {
+            let kb = kb.read().expect("cannot acquire KB read lock");
+            (
+                Arc::new(kb.get_registered_constants().clone()),
+                kb.rules_snapshot(),
+                kb.get_operators().clone(),
+                Arc::new(kb.resource_blocks.actors.clone()),
+                Arc::new(kb.resource_blocks.resources.clone()),
+            )
+        };
 
         let mut vm = Self {
             goals: GoalStack::new_reversed(goals),
@@ -318,6 +415,18 @@ impl PolarVirtualMachine {
             query_start_time: None,
             query_timeout_ms,
             stack_limit: MAX_STACK_SIZE,
+            strict_float_arithmetic: false,
+            forbid_calls_in_negation: false,
+            max_term_size: None,
+            max_applicable_rules: None,
+            allowed_rules: None,
+            occurs_check: false,
+            raw_bindings: false,
+            backtrack_observer: None,
+            progress: Rc::default(),
+            progress_observer: None,
+            rule_mismatch_observer: None,
+            rule_checks_passed: 0,
             csp: Bsp::default(),
             choices: vec![],
             queries: vec![],
@@ -327,6 +436,11 @@ impl PolarVirtualMachine {
             external_error: None,
             debugger: Debugger::default(),
             kb,
+            rules,
+            operators,
+            constants: constants.clone(),
+            actors,
+            resources,
             call_id_symbols: HashMap::new(),
             // `log` controls internal VM logging
             log_level: None,
@@ -337,7 +451,7 @@ impl PolarVirtualMachine {
             inverting: false,
             messages,
         };
-        vm.bind_constants(constants);
+        vm.bind_constants(&constants);
         vm.query_contains_partial();
 
         let polar_log = std::env::var("POLAR_LOG");
@@ -415,16 +529,111 @@ impl PolarVirtualMachine {
     pub fn clone_with_goals(&self, goals: Goals) -> Self {
         let mut vm = Self::new(self.kb.clone(), self.tracing, goals, self.messages.clone());
         vm.binding_manager.clone_from(&self.binding_manager);
+        vm.rules = self.rules.clone();
+        vm.operators = self.operators.clone();
+        vm.constants = self.constants.clone();
+        vm.actors = self.actors.clone();
+        vm.resources = self.resources.clone();
         vm.query_contains_partial = self.query_contains_partial;
+        vm.strict_float_arithmetic = self.strict_float_arithmetic;
+        vm.forbid_calls_in_negation = self.forbid_calls_in_negation;
+        vm.stack_limit = self.stack_limit;
+        vm.max_term_size = self.max_term_size;
+        vm.max_applicable_rules = self.max_applicable_rules;
+        vm.allowed_rules = self.allowed_rules.clone();
+        vm.occurs_check = self.occurs_check;
+        vm.query_timeout_ms = self.query_timeout_ms;
+        vm.backtrack_observer = self.backtrack_observer.clone();
+        vm.progress = self.progress.clone();
+        vm.progress_observer = self.progress_observer.clone();
+        vm.rule_mismatch_observer = self.rule_mismatch_observer.clone();
         vm.debugger = self.debugger.clone();
         vm
     }
 
-    #[cfg(test)]
-    fn set_stack_limit(&mut self, limit: usize) {
+    /// Override the `POLAR_TIMEOUT_MS` wall-clock budget for this query.
+    /// The budget covers time spent waiting on the host, and is checked
+    /// before every goal, so a slow external call is caught before the next
+    /// one is made. A timeout of 0 disables the check.
+    pub fn set_query_timeout_ms(&mut self, timeout_ms: u64) {
+        self.query_timeout_ms = timeout_ms;
+    }
+
+    /// If `strict` is set, arithmetic that produces NaN or ±∞ (e.g., `1 / 0`)
+    /// raises an `ArithmeticError` instead of returning a non-finite float.
+    pub fn set_strict_float_arithmetic(&mut self, strict: bool) {
+        self.strict_float_arithmetic = strict;
+    }
+
+    /// If `forbid` is set, calling an external method inside a `not` or `forall`
+    /// raises an error, since any side effects of the call would be discarded
+    /// along with its result. Attribute lookups are still allowed.
+    pub fn set_forbid_calls_in_negation(&mut self, forbid: bool) {
+        self.forbid_calls_in_negation = forbid;
+    }
+
+    /// Limit the depth of the goal and choice stacks, which defaults to
+    /// `MAX_STACK_SIZE`. Deeply (or infinitely) recursive rules fail with a
+    /// `StackOverflow` error once either stack reaches the limit.
+    pub fn set_stack_limit(&mut self, limit: usize) {
         self.stack_limit = limit;
     }
 
+    /// Limit the size of the terms that can be unified, to stop runaway
+    /// recursive rules from building arbitrarily large terms. Unifying a
+    /// larger term raises a `TermSizeExceeded` error.
+    pub fn set_max_term_size(&mut self, max_term_size: Option<usize>) {
+        self.max_term_size = max_term_size;
+    }
+
+    /// Limit the number of rules a single call may apply to, to catch
+    /// policies that accidentally generate far more rules than intended.
+    /// Such calls raise a `TooManyApplicableRules` error.
+    pub fn set_max_applicable_rules(&mut self, max_applicable_rules: Option<usize>) {
+        self.max_applicable_rules = max_applicable_rules;
+    }
+
+    /// Only allow calls to the named rules; calling any other rule is an
+    /// error. Useful for sandboxing untrusted or per-tenant policies.
+    pub fn set_allowed_rules(&mut self, allowed_rules: Option<HashSet<Symbol>>) {
+        self.allowed_rules = allowed_rules;
+    }
+
+    /// By default, unifying a variable with a term that contains it (e.g.,
+    /// `x = [x]`) creates a cyclic term. If `occurs_check` is set, such
+    /// unifications fail instead, at the cost of walking the term on every
+    /// binding.
+    pub fn set_occurs_check(&mut self, occurs_check: bool) {
+        self.occurs_check = occurs_check;
+    }
+
+    /// If `raw` is set, results contain every binding exactly as it was
+    /// made, including temporaries, references to other variables, and
+    /// constraints, rather than fully resolved and simplified values.
+    pub fn set_raw_bindings(&mut self, raw: bool) {
+        self.raw_bindings = raw;
+    }
+
+    /// Call `observer` with the innermost query term each time a branch
+    /// fails and is abandoned. Moving on to the next result after a
+    /// success is not reported.
+    pub fn set_backtrack_observer(&mut self, observer: Option<BacktrackObserver>) {
+        self.backtrack_observer = observer;
+    }
+
+    /// Call the observer with the query's progress every `n` choice points.
+    pub fn set_progress_observer(&mut self, observer: Option<(usize, ProgressObserver)>) {
+        self.progress_observer = observer;
+    }
+
+    /// Call `observer` each time a rule is considered for a call but
+    /// doesn't apply to its arguments, with the first check that failed.
+    /// Rules that the rule index rules out on ground arguments are never
+    /// considered, so they aren't reported.
+    pub fn set_rule_mismatch_observer(&mut self, observer: Option<RuleMismatchObserver>) {
+        self.rule_mismatch_observer = observer;
+    }
+
     fn kb(&self) -> RwLockReadGuard<KnowledgeBase> {
         self.kb.read().unwrap()
     }
@@ -464,7 +673,10 @@ impl PolarVirtualMachine {
         self.check_timeout()?;
 
         match goal.as_ref() {
-            Goal::Backtrack => self.backtrack()?,
+            Goal::Backtrack => {
+                self.observe_backtrack();
+                self.backtrack()?
+            }
             Goal::Cut { choice_index } => self.cut(*choice_index),
             Goal::Debug { message } => return Ok(self.debug(message)),
             Goal::Halt => return Ok(self.halt()),
@@ -486,6 +698,7 @@ impl PolarVirtualMachine {
                 field,
             } => return self.lookup_external(*call_id, instance, field),
             Goal::IsaExternal { instance, literal } => return self.isa_external(instance, literal),
+            Goal::CallOperator { name, args } => return self.call_operator(name, args),
             Goal::MakeExternal {
                 constructor,
                 instance_id,
@@ -528,11 +741,16 @@ impl PolarVirtualMachine {
             Goal::TraceRule { trace } => {
                 if let Node::Rule(rule) = &trace.node {
                     self.log(LogLevel::Info, || format!("RULE: {}", rule), &[]);
+                    self.update_progress(|p| p.rules_applied += 1);
                 }
                 self.trace.push(trace.clone());
                 self.maybe_break(DebugEvent::Rule)?;
             }
-            Goal::Unify { left, right } => self.unify(left, right)?,
+            Goal::Unify { left, right } => {
+                self.check_term_size(left)?;
+                self.check_term_size(right)?;
+                self.unify(left, right)?
+            }
             Goal::AddConstraint { term } => self.add_constraint(term)?,
             Goal::AddConstraintsBatch { add_constraints } => {
                 add_constraints
@@ -541,6 +759,20 @@ impl PolarVirtualMachine {
                     .try_for_each(|(_, constraint)| self.add_constraint(&constraint))?
             }
             Goal::Run { runnable } => return self.run_runnable(runnable.clone_runnable()),
+            Goal::RuleChecksPassed { checks } => self.rule_checks_passed = *checks,
+            Goal::ReportRuleMismatch { rule, reasons } => {
+                if let Some(reason) = reasons.get(self.rule_checks_passed) {
+                    self.report_rule_mismatch(rule, reason.clone());
+                }
+            }
+            Goal::AssertionSucceeded { succeeded } => succeeded.set(true),
+            Goal::CheckAssertion { succeeded, term } => {
+                if succeeded.get() {
+                    self.push_goal(Goal::Backtrack)?;
+                } else {
+                    return Err(RuntimeError::AssertionFailed { term: term.clone() }.into());
+                }
+            }
         }
         Ok(QueryEvent::None)
     }
@@ -592,7 +824,16 @@ impl PolarVirtualMachine {
                 queries: self.queries.clone(),
                 trace: self.trace.clone(),
                 trace_stack: self.trace_stack.clone(),
+                conditional: false,
             });
+            let progress = self.update_progress(|p| p.choice_points += 1);
+            // `usize::is_multiple_of` needs Rust 1.87.
+            #[allow(unknown_lints, clippy::manual_is_multiple_of)]
+            if let Some((every, observer)) = &self.progress_observer {
+                if progress.choice_points % *every == 0 {
+                    observer(&progress);
+                }
+            }
             Ok(())
         }
     }
@@ -614,6 +855,7 @@ impl PolarVirtualMachine {
             self.push_choice(alternatives_iter)?;
             self.append_goals(alternative)
         } else {
+            self.observe_backtrack();
             self.backtrack()
         }
     }
@@ -642,7 +884,11 @@ impl PolarVirtualMachine {
         conditional.push(cut_alternative);
         conditional.push(Goal::Backtrack);
 
-        self.choose(vec![conditional, alternative])
+        // Backtracking into either choice is control flow, not a failed branch.
+        self.choices.last_mut().unwrap().conditional = true;
+        self.choose(vec![conditional, alternative])?;
+        self.choices.last_mut().unwrap().conditional = true;
+        Ok(())
     }
 
     /// Push multiple goals onto the stack in reverse order.
@@ -675,6 +921,41 @@ impl PolarVirtualMachine {
         }
     }
 
+    /// Error if `term` has more than `max_term_size` nodes once its variables
+    /// are replaced by their values. Each variable's value is only counted
+    /// once, so this terminates on cyclic data.
+    fn check_term_size(&self, term: &Term) -> PolarResult<()> {
+        let max = match self.max_term_size {
+            Some(max) => max,
+            None => return Ok(()),
+        };
+        let mut seen = HashSet::new();
+        let mut stack = vec![term.clone()];
+        let mut size = 0;
+        while let Some(term) = stack.pop() {
+            size += 1;
+            if size > max {
+                return Err(RuntimeError::TermSizeExceeded { max }.into());
+            }
+            match term.value() {
+                Value::Variable(v) | Value::RestVariable(v) if seen.insert(v.clone()) => {
+                    if let VariableState::Bound(val) = self.variable_state(v) {
+                        stack.push(val);
+                    }
+                }
+                Value::List(terms) => stack.extend(terms.iter().cloned()),
+                Value::Dictionary(dict) => stack.extend(dict.fields.values().cloned()),
+                Value::Call(call) => {
+                    stack.extend(call.args.iter().cloned());
+                    stack.extend(call.kwargs.iter().flat_map(|k| k.values().cloned()));
+                }
+                Value::Expression(op) => stack.extend(op.args.iter().cloned()),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
     pub fn add_binding_follower(&mut self) -> FollowerId {
         self.binding_manager.add_follower(BindingManager::new())
     }
@@ -697,7 +978,7 @@ impl PolarVirtualMachine {
 
     /// Augment the bindings stack with constants from a hash map.
     /// There must be no temporaries bound yet.
-    fn bind_constants(&mut self, bindings: Bindings) {
+    fn bind_constants(&mut self, bindings: &Bindings) {
         assert_eq!(self.bsp(), self.csp);
         for (var, value) in bindings.iter() {
             self.bind(var, value.clone()).unwrap();
@@ -711,6 +992,11 @@ impl PolarVirtualMachine {
             .bindings_after(include_temps, &self.csp)
     }
 
+    /// Retrieve the current non-constant bindings without dereferencing them.
+    pub fn raw_bindings(&self) -> Bindings {
+        self.binding_manager.raw_bindings_after(&self.csp)
+    }
+
     /// Retrive internal binding stack for debugger.
     pub fn bindings_debug(&self) -> &BindingStack {
         self.binding_manager.bindings_debug()
@@ -735,6 +1021,11 @@ impl PolarVirtualMachine {
         self.binding_manager.variable_state_at_point(variable, bsp)
     }
 
+    /// Whether any variable may have been partially bound at `bsp`.
+    pub fn has_partials_at_point(&self, bsp: &Bsp) -> bool {
+        self.binding_manager.has_partials_at_point(bsp)
+    }
+
     /// Investigate the current state of a variable and return a variable state variant.
     fn variable_state(&self, variable: &Symbol) -> VariableState {
         self.binding_manager.variable_state(variable)
@@ -904,6 +1195,51 @@ impl PolarVirtualMachine {
 
 /// Implementations of instructions.
 impl PolarVirtualMachine {
+    /// Report a failed branch to the backtrack observer, unless we're about
+    /// to backtrack into a choice made by `choose_conditional`, which uses
+    /// backtracking for control flow rather than to abandon a branch.
+    fn observe_backtrack(&self) {
+        if let (Some(observer), Some(term)) = (&self.backtrack_observer, self.queries.last()) {
+            let next_choice = self
+                .choices
+                .iter()
+                .rev()
+                .find(|choice| !choice.alternatives.is_empty());
+            if !matches!(next_choice, Some(choice) if choice.conditional) {
+                observer(term);
+            }
+        }
+    }
+
+    /// Update the query's progress and return the new counts.
+    fn update_progress<F: FnOnce(&mut QueryProgress)>(&self, f: F) -> QueryProgress {
+        let mut progress = self.progress.get();
+        f(&mut progress);
+        self.progress.set(progress);
+        progress
+    }
+
+    /// Report a rule that doesn't apply to the rule mismatch observer, with
+    /// the arguments in the reason dereferenced.
+    fn report_rule_mismatch(&self, rule: &Arc<Rule>, reason: RuleMismatchReason) {
+        if let Some(observer) = &self.rule_mismatch_observer {
+            let reason = match reason {
+                RuleMismatchReason::Unify { arg, param } => RuleMismatchReason::Unify {
+                    arg: self.deref(&arg),
+                    param,
+                },
+                RuleMismatchReason::Isa { arg, specializer } => RuleMismatchReason::Isa {
+                    arg: self.deref(&arg),
+                    specializer,
+                },
+            };
+            observer(&RuleMismatch {
+                rule: rule.clone(),
+                reason,
+            });
+        }
+    }
+
     /// Remove all bindings after the last choice point, and try the
     /// next available alternative. If no choice is possible, halt.
     fn backtrack(&mut self) -> PolarResult<()> {
@@ -919,6 +1255,7 @@ impl PolarVirtualMachine {
                     queries,
                     trace,
                     trace_stack,
+                    conditional,
                 }) => {
                     self.binding_manager.backtrack(&bsp);
                     if let Some(mut alternative) = alternatives.pop() {
@@ -939,6 +1276,7 @@ impl PolarVirtualMachine {
                                 queries,
                                 trace,
                                 trace_stack,
+                                conditional,
                             })
                         }
                         self.goals.append(&mut alternative);
@@ -989,11 +1327,18 @@ impl PolarVirtualMachine {
 
         match (left.value(), right.value()) {
             (_, Value::Dictionary(_)) => todo!("make this case unreachable"),
+            (
+                _,
+                Value::Expression(Operation {
+                    operator: Operator::Or,
+                    args,
+                }),
+            ) => self.isa_any(left, args)?,
             (Value::Expression(_), _) | (_, Value::Expression(_)) => {
                 unreachable!("encountered bare expression")
             }
 
-            _ if self.kb.read().unwrap().is_union(left) => {
+            _ if left.is_union() => {
                 // A union (currently) only matches itself.
                 //
                 // TODO(gj): when we have unions beyond `Actor` and `Resource`, we'll need to be
@@ -1005,7 +1350,7 @@ impl PolarVirtualMachine {
                     return self.push_goal(Goal::Backtrack);
                 }
             }
-            _ if self.kb.read().unwrap().is_union(right) => self.isa_union(left, right)?,
+            _ if right.is_union() => self.isa_union(left, right)?,
 
             // TODO(gj): (Var, Rest) + (Rest, Var) cases might be unreachable.
             (Value::Variable(l), Value::Variable(r))
@@ -1285,25 +1630,38 @@ impl PolarVirtualMachine {
     /// To evaluate `left matches Union`, look up `Union`'s member classes and create a choicepoint
     /// to check if `left` matches any of them.
     fn isa_union(&mut self, left: &Term, union: &Term) -> PolarResult<()> {
-        let member_isas = {
-            let kb = self.kb.read().unwrap();
-            let members = kb.get_union_members(union).iter();
-            members
-                .map(|member| {
-                    let tag = member.as_symbol().unwrap().0.as_str();
-                    member.clone_with_value(value!(pattern!(instance!(tag))))
-                })
-                .map(|pattern| {
-                    vec![Goal::Isa {
-                        left: left.clone(),
-                        right: pattern,
-                    }]
-                })
-                .collect::<Vec<Goals>>()
+        let members = if union.is_actor_union() {
+            self.actors.clone()
+        } else {
+            self.resources.clone()
         };
+        let member_isas = members
+            .iter()
+            .map(|member| {
+                let tag = member.as_symbol().unwrap().0.as_str();
+                member.clone_with_value(value!(pattern!(instance!(tag))))
+            })
+            .map(|pattern| {
+                vec![Goal::Isa {
+                    left: left.clone(),
+                    right: pattern,
+                }]
+            })
+            .collect::<Vec<Goals>>();
         self.choose(member_isas)
     }
 
+    /// To evaluate `left matches (A or B)`, create a choicepoint to check if `left` matches each
+    /// pattern. There is one solution per matching pattern.
+    fn isa_any(&mut self, left: &Term, patterns: &[Term]) -> PolarResult<()> {
+        self.choose(patterns.iter().map(|pattern| {
+            vec![Goal::Isa {
+                left: left.clone(),
+                right: pattern.clone(),
+            }]
+        }))
+    }
+
     fn lookup(&mut self, dict: &Dictionary, field: &Term, value: &Term) -> PolarResult<()> {
         let field = self.deref(field);
         match field.value() {
@@ -1377,6 +1735,29 @@ impl PolarVirtualMachine {
             }
         };
 
+        // The host can't receive a variable, so refuse to call it with one.
+        let unbound = args
+            .iter()
+            .flatten()
+            .chain(kwargs.iter().flat_map(|kwargs| kwargs.values()))
+            .find(|arg| matches!(arg.value(), Value::Variable(_) | Value::RestVariable(_)));
+        if let Some(arg) = unbound {
+            return self.type_error(
+                arg,
+                format!(
+                    "host method {} called with unbound argument {}",
+                    field_name, arg
+                ),
+            );
+        }
+
+        if self.inverting && self.forbid_calls_in_negation && args.is_some() {
+            return unsupported(
+                format!("calling external method {} inside a negation", field_name),
+                field,
+            );
+        }
+
         // add an empty choice point; lookups return only one value
         // but we'll want to cut if we get back nothing
         self.push_choice(vec![])?;
@@ -1556,7 +1937,19 @@ impl PolarVirtualMachine {
                 predicate
             ));
         }
-        let goals = match self.kb.read().unwrap().get_generic_rule(&predicate.name) {
+        if let Some(allowed_rules) = &self.allowed_rules {
+            if !allowed_rules.contains(&predicate.name) {
+                return Err(RuntimeError::QueryForDisallowedRule {
+                    name: predicate.name.0,
+                }
+                .into());
+            }
+        }
+        if !self.rules.contains_key(&predicate.name) && self.operators.contains(&predicate.name) {
+            return self.query_for_operator(predicate);
+        }
+        let rules = self.rules.clone();
+        let goals = match rules.get(&predicate.name) {
             None => {
                 return Err(RuntimeError::QueryForUndefinedRule {
                     name: predicate.name.0.clone(),
@@ -1574,6 +1967,16 @@ impl PolarVirtualMachine {
                 // Pre-filter rules.
                 let args = predicate.args.iter().map(|t| self.deref(t)).collect();
                 let pre_filter = generic_rule.get_applicable_rules(&args);
+                if let Some(max) = self.max_applicable_rules {
+                    if pre_filter.len() > max {
+                        return Err(RuntimeError::TooManyApplicableRules {
+                            name: predicate.name.0.clone(),
+                            count: pre_filter.len(),
+                            max,
+                        }
+                        .into());
+                    }
+                }
 
                 self.polar_trace_mute = true;
 
@@ -1592,6 +1995,41 @@ impl PolarVirtualMachine {
         self.append_goals(goals)
     }
 
+    /// Ask the host whether a registered operator holds for its arguments.
+    fn query_for_operator(&mut self, predicate: Call) -> PolarResult<()> {
+        if predicate.args.len() != 2 {
+            let msg = format!(
+                "operator {} takes 2 arguments, got {}",
+                predicate.name,
+                predicate.args.len()
+            );
+            return self.type_error(&Term::from(predicate), msg);
+        }
+        self.push_goal(Goal::CallOperator {
+            name: predicate.name,
+            args: predicate.args,
+        })
+    }
+
+    fn call_operator(&mut self, name: &Symbol, args: &[Term]) -> PolarResult<QueryEvent> {
+        let args = args.iter().map(|arg| self.deref(arg)).collect::<TermList>();
+        if let Some(arg) = args.iter().find(|arg| arg.as_symbol().is_ok()) {
+            let msg = format!("arguments to operator {} must be bound", name);
+            return self.type_error(arg, msg);
+        }
+        let (call_id, answer) = self.new_call_var("operator_result", false.into());
+        self.push_goal(Goal::Unify {
+            left: answer,
+            right: Term::from(true),
+        })?;
+
+        Ok(QueryEvent::ExternalOperator {
+            call_id,
+            name: name.clone(),
+            args,
+        })
+    }
+
     fn query_for_operation(&mut self, term: &Term) -> PolarResult<QueryEvent> {
         let operation = term.as_expression().unwrap();
         let mut args = operation.args.clone();
@@ -1688,7 +2126,7 @@ impl PolarVirtualMachine {
             }
 
             Operator::Debug => {
-                let message = self.debugger.break_msg(self).unwrap_or_else(|| {
+                let mut message = self.debugger.break_msg(self).unwrap_or_else(|| {
                     format!(
                         "debug({})",
                         args.iter()
@@ -1697,6 +2135,12 @@ impl PolarVirtualMachine {
                             .join(", ")
                     )
                 });
+                // With no arguments, also show the bindings of the enclosing rule.
+                if args.is_empty() {
+                    for binding in self.rule_bindings() {
+                        message.push_str(&format!("\n{}", binding));
+                    }
+                }
                 self.push_goal(Goal::Debug { message })?;
             }
             Operator::Print => {
@@ -1719,7 +2163,7 @@ impl PolarVirtualMachine {
                 let instance_id = self.new_id();
 
                 let class = &constructor.as_call()?.name;
-                let class_repr = if self.kb().is_constant(class) {
+                let class_repr = if self.constants.contains_key(class) {
                     Some(class.0.clone())
                 } else {
                     None
@@ -1752,11 +2196,19 @@ impl PolarVirtualMachine {
                 }
 
                 // Remove all choices created before this cut that are in the
-                // current rule body.
+                // current rule body, including those for any disjunctions the
+                // cut is nested in, and the choice of the remaining rules.
+                //
+                // The comparison prefix ends with the query for the current rule,
+                // excluding the rule body and any expressions within it.
+                let rule_depth = self
+                    .queries
+                    .iter()
+                    .rposition(|q| matches!(q.value(), Value::Call(_)))
+                    .map_or(0, |i| i + 1);
+                let prefix = &self.queries[..rule_depth];
                 let mut choice_index = self.choices.len();
                 for choice in self.choices.iter().rev() {
-                    // Comparison excludes the rule body & cut operator (the last two elements of self.queries)
-                    let prefix = &self.queries[..(self.queries.len() - 2)];
                     if choice.queries.starts_with(prefix) {
                         // If the choice has the same query stack as the current
                         // query stack, remove it.
@@ -1802,6 +2254,25 @@ impl PolarVirtualMachine {
                     term: double_negation,
                 })?;
             }
+            Operator::Assert => {
+                // Query the condition as usual, with a last alternative that
+                // raises an error if the condition never produced a solution.
+                if args.len() != 1 {
+                    return wrong_arity();
+                }
+
+                let term = args.pop().unwrap();
+                let succeeded = Rc::new(Cell::new(false));
+                self.choose(vec![
+                    vec![
+                        Goal::Query { term: term.clone() },
+                        Goal::AssertionSucceeded {
+                            succeeded: succeeded.clone(),
+                        },
+                    ],
+                    vec![Goal::CheckAssertion { succeeded, term }],
+                ])?;
+            }
         }
         Ok(QueryEvent::None)
     }
@@ -1964,6 +2435,56 @@ impl PolarVirtualMachine {
                     args: vec![left.clone(), right.clone()],
                 })
             }
+            (Value::List(l), Value::List(r))
+                if matches!(op, Operator::Eq) && !has_rest_var(l) && !has_rest_var(r) =>
+            {
+                // Lists are equal if they're the same length & their elements are pairwise equal.
+                if l.len() == r.len() {
+                    let pairs = l.iter().zip(r).map(|(l, r)| op!(Eq, l.clone(), r.clone()));
+                    self.push_goal(Goal::Query {
+                        term: term.clone_with_value(Value::Expression(Operation {
+                            operator: Operator::And,
+                            args: pairs.map(Term::from).collect(),
+                        })),
+                    })?;
+                } else {
+                    self.push_goal(Goal::Backtrack)?;
+                }
+                Ok(QueryEvent::None)
+            }
+            (Value::Dictionary(l), Value::Dictionary(r)) if matches!(op, Operator::Eq) => {
+                // Dictionaries are equal if they have the same keys & their values are pairwise equal.
+                if l.fields.keys().eq(r.fields.keys()) {
+                    let pairs = l
+                        .fields
+                        .values()
+                        .zip(r.fields.values())
+                        .map(|(l, r)| op!(Eq, l.clone(), r.clone()));
+                    self.push_goal(Goal::Query {
+                        term: term.clone_with_value(Value::Expression(Operation {
+                            operator: Operator::And,
+                            args: pairs.map(Term::from).collect(),
+                        })),
+                    })?;
+                } else {
+                    self.push_goal(Goal::Backtrack)?;
+                }
+                Ok(QueryEvent::None)
+            }
+            (Value::List(_), Value::List(_)) | (Value::Dictionary(_), Value::Dictionary(_))
+                if matches!(op, Operator::Neq)
+                    && ![left, right]
+                        .iter()
+                        .any(|t| matches!(t.value(), Value::List(l) if has_rest_var(l))) =>
+            {
+                // `l != r` iff `not l == r`.
+                let eq =
+                    term.clone_with_value(Value::Expression(op!(Eq, left.clone(), right.clone())));
+                self.push_goal(Goal::Query {
+                    term: term.clone_with_value(Value::Expression(op!(Not, eq))),
+                })?;
+                Ok(QueryEvent::None)
+            }
             _ => {
                 if !compare(*op, left, right, Some(term))? {
                     self.push_goal(Goal::Backtrack)?;
@@ -1984,8 +2505,9 @@ impl PolarVirtualMachine {
         }
         let left = &args[0];
         let right = &args[1];
+        // `result` is usually a fresh variable, but may already be bound (or
+        // be a value); either way the answer is unified with it.
         let result = &args[2];
-        result.as_symbol()?; // Ensure `result` is a variable.
 
         match (left.value(), right.value()) {
             (Value::Number(left), Value::Number(right)) => {
@@ -1997,7 +2519,11 @@ impl PolarVirtualMachine {
                     Operator::Mod => (*left).modulo(*right),
                     Operator::Rem => *left % *right,
                     _ => return unsupported(format!("numeric operation {}", op), term),
-                } {
+                }
+                .filter(|answer| {
+                    !self.strict_float_arithmetic
+                        || !matches!(answer, Numeric::Float(f) if !f.is_finite())
+                }) {
                     self.push_goal(Goal::Unify {
                         left: term.clone_with_value(Value::Number(answer)),
                         right: result.clone(),
@@ -2007,6 +2533,13 @@ impl PolarVirtualMachine {
                     Err(RuntimeError::ArithmeticError { term: term.clone() }.into())
                 }
             }
+            (Value::String(left), Value::String(right)) if *op == Operator::Add => {
+                self.push_goal(Goal::Unify {
+                    left: term.clone_with_value(Value::String(format!("{}{}", left, right))),
+                    right: result.clone(),
+                })?;
+                Ok(QueryEvent::None)
+            }
             (_, _) => unsupported(format!("unsupported arithmetic operands: {}", term), term),
         }
     }
@@ -2023,6 +2556,26 @@ impl PolarVirtualMachine {
         let field = &args[1];
         let value = &args[2];
 
+        // Index into lists directly. Negative indices count back from the
+        // end of the list, and indices out of range have no solution.
+        if let (Value::List(list), Value::Number(Numeric::Integer(index))) =
+            (object.value(), field.value())
+        {
+            if !has_rest_var(list) {
+                let len = list.len() as i64;
+                let index = if *index < 0 { index + len } else { *index };
+                if (0..len).contains(&index) {
+                    self.push_goal(Goal::Unify {
+                        left: value.clone(),
+                        right: list[index as usize].clone(),
+                    })?;
+                } else {
+                    self.push_goal(Goal::Backtrack)?;
+                }
+                return Ok(QueryEvent::None);
+            }
+        }
+
         match object.value() {
             // Push a `Lookup` goal for simple field lookups on dictionaries.
             Value::Dictionary(dict)
@@ -2133,21 +2686,28 @@ impl PolarVirtualMachine {
                     })
                     .collect::<Vec<Goals>>(),
             )?,
-            // Unify item with each element of the string
-            // FIXME (gw): this seems strange, wouldn't a substring search make more sense?
-            Value::String(s) => self.choose(
-                s.chars()
-                    .map(|c| c.to_string())
-                    .map(Value::String)
-                    .filter(|c| !item_is_ground || c == item.value())
-                    .map(|c| {
-                        vec![Goal::Unify {
-                            left: item.clone(),
-                            right: iterable.clone_with_value(c),
-                        }]
-                    })
-                    .collect::<Vec<Goals>>(),
-            )?,
+            Value::String(s) => match item.value() {
+                // Search for a string item as a substring, succeeding at most once.
+                Value::String(sub) => {
+                    if !s.contains(sub.as_str()) {
+                        self.push_goal(Goal::Backtrack)?;
+                    }
+                }
+                // Otherwise unify item with each character of the string.
+                _ => self.choose(
+                    s.chars()
+                        .map(|c| c.to_string())
+                        .map(Value::String)
+                        .filter(|c| !item_is_ground || c == item.value())
+                        .map(|c| {
+                            vec![Goal::Unify {
+                                left: item.clone(),
+                                right: iterable.clone_with_value(c),
+                            }]
+                        })
+                        .collect::<Vec<Goals>>(),
+                )?,
+            },
             // Push an `ExternalLookup` goal for external instances
             Value::ExternalInstance(_) => {
                 // Generate symbol for next result and leave the variable unbound, so that unification with the result does not fail
@@ -2242,6 +2802,18 @@ impl PolarVirtualMachine {
                             // Both variables are bound. Unify their values.
                             self.push_goal(Goal::Unify { left: x, right: y })?;
                         }
+                        // One variable is bound. Binding the other to it must
+                        // not make its value cyclic.
+                        (VariableState::Bound(_), _)
+                            if self.occurs_check && self.binding_manager.occurs(r, left) =>
+                        {
+                            self.push_goal(Goal::Backtrack)?;
+                        }
+                        (_, VariableState::Bound(_))
+                            if self.occurs_check && self.binding_manager.occurs(l, right) =>
+                        {
+                            self.push_goal(Goal::Backtrack)?;
+                        }
                         _ => {
                             // At least one variable is unbound. Bind it.
                             if self.bind(l, right.clone()).is_err() {
@@ -2265,6 +2837,9 @@ impl PolarVirtualMachine {
                     VariableState::Bound(value) => {
                         self.push_goal(Goal::Unify { left: value, right })?;
                     }
+                    _ if self.occurs_check && self.binding_manager.occurs(var, &right) => {
+                        self.push_goal(Goal::Backtrack)?;
+                    }
                     _ => {
                         if self.bind(var, right).is_err() {
                             self.push_goal(Goal::Backtrack)?;
@@ -2280,6 +2855,9 @@ impl PolarVirtualMachine {
                     VariableState::Bound(value) => {
                         self.push_goal(Goal::Unify { left, right: value })?;
                     }
+                    _ if self.occurs_check && self.binding_manager.occurs(var, &left) => {
+                        self.push_goal(Goal::Backtrack)?;
+                    }
                     _ => {
                         if self.bind(var, left).is_err() {
                             self.push_goal(Goal::Backtrack)?;
@@ -2526,19 +3104,41 @@ impl PolarVirtualMachine {
             // This avoids clashes between arg vars and rule vars.
             let Rule { params, .. } = self.rename_rule_vars(&rule);
             let mut check_applicability = vec![];
-            for (arg, param) in args.iter().zip(params.iter()) {
+            let mut reasons = vec![];
+            for ((arg, param), original) in args.iter().zip(params.iter()).zip(&rule.params) {
                 check_applicability.push(Goal::Unify {
                     left: arg.clone(),
                     right: param.parameter.clone(),
                 });
+                reasons.push(RuleMismatchReason::Unify {
+                    arg: arg.clone(),
+                    param: original.parameter.clone(),
+                });
                 if let Some(specializer) = &param.specializer {
                     check_applicability.push(Goal::Isa {
                         left: arg.clone(),
                         right: specializer.clone(),
                     });
+                    reasons.push(RuleMismatchReason::Isa {
+                        arg: arg.clone(),
+                        specializer: original.specializer.clone().unwrap(),
+                    });
+                }
+            }
+
+            let mut inapplicable = vec![inapplicable];
+            if self.rule_mismatch_observer.is_some() {
+                // Note each check as it passes, so that if one fails we can
+                // tell which.
+                let mut checks = vec![Goal::RuleChecksPassed { checks: 0 }];
+                for (i, check) in check_applicability.into_iter().enumerate() {
+                    checks.push(check);
+                    checks.push(Goal::RuleChecksPassed { checks: i + 1 });
                 }
+                check_applicability = checks;
+                inapplicable.insert(0, Goal::ReportRuleMismatch { rule, reasons });
             }
-            self.choose_conditional(check_applicability, vec![applicable], vec![inapplicable])?;
+            self.choose_conditional(check_applicability, vec![applicable], inapplicable)?;
             Ok(())
         }
     }
@@ -2676,17 +3276,14 @@ impl PolarVirtualMachine {
                 // smarter about this check since UnionA is more specific than UnionB if UnionA is
                 // a member of UnionB.
                 (Some(left_spec), Some(right_spec))
-                    if self.kb.read().unwrap().is_union(left_spec)
-                        && self.kb.read().unwrap().is_union(right_spec) => {}
+                    if left_spec.is_union() && right_spec.is_union() => {}
                 // If left is a union and right is not, left cannot be more specific, so we
                 // backtrack.
-                (Some(left_spec), Some(_)) if self.kb.read().unwrap().is_union(left_spec) => {
+                (Some(left_spec), Some(_)) if left_spec.is_union() => {
                     return self.push_goal(Goal::Backtrack)
                 }
                 // If right is a union and left is not, left IS more specific, so we return.
-                (Some(_), Some(right_spec)) if self.kb.read().unwrap().is_union(right_spec) => {
-                    return Ok(())
-                }
+                (Some(_), Some(right_spec)) if right_spec.is_union() => return Ok(()),
 
                 (Some(left_spec), Some(right_spec)) => {
                     // If you find two non-equal specializers, that comparison determines the relative
@@ -2894,8 +3491,12 @@ impl Runnable for PolarVirtualMachine {
             None
         };
 
-        let mut bindings = self.bindings(true);
-        if !self.inverting {
+        let mut bindings = if self.raw_bindings {
+            self.raw_bindings()
+        } else {
+            self.bindings(true)
+        };
+        if !self.inverting && !self.raw_bindings {
             match simplify_bindings_opt(bindings, false) {
                 Ok(Some(bs)) => {
                     // simplification succeeds
@@ -2983,6 +3584,9 @@ impl Runnable for PolarVirtualMachine {
             &[],
         );
 
+        if !self.inverting {
+            self.update_progress(|p| p.results += 1);
+        }
         Ok(QueryEvent::Result { bindings, trace })
     }
 
@@ -3214,6 +3818,28 @@ mod tests {
         assert_query_events!(vm, [QueryEvent::Done { result: true }]);
     }
 
+    #[test]
+    fn empty_or_expression() {
+        let mut vm = PolarVirtualMachine::default();
+        vm.push_goal(query!(op!(Or))).unwrap();
+        assert_query_events!(vm, [QueryEvent::Done { result: true }]);
+    }
+
+    #[test]
+    fn arithmetic_with_bound_result() {
+        let mut vm = PolarVirtualMachine::default();
+        vm.push_goal(query!(op!(Add, term!(1), term!(2), term!(3))))
+            .unwrap();
+        assert_query_events!(vm, [
+            QueryEvent::Result{hashmap!{}},
+            QueryEvent::Done { result: true }
+        ]);
+
+        vm.push_goal(query!(op!(Add, term!(1), term!(2), term!(4))))
+            .unwrap();
+        assert_query_events!(vm, [QueryEvent::Done { result: true }]);
+    }
+
     #[test]
     #[allow(clippy::cognitive_complexity)]
     fn isa_on_lists() {
@@ -1,11 +1,11 @@
 mod mock_externals;
 
 use indoc::indoc;
-use maplit::btreemap;
+use maplit::{btreemap, hashset};
 use permute::permute;
 
 use std::cell::RefCell;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use mock_externals::MockExternal;
 use polar_core::{
@@ -14,6 +14,7 @@ use polar_core::{
     messages::*,
     polar::Polar,
     query::Query,
+    sources::{Context, Source, SourceInfo},
     sym, term,
     terms::*,
     traces::*,
@@ -286,6 +287,33 @@ fn eval(p: &Polar, query_str: &str) -> bool {
     !query_results!(q).is_empty()
 }
 
+/// Run `query_str` with the options set by `configure`, returning the first
+/// error instead of panicking on it.
+fn query_with<F>(p: &Polar, query_str: &str, configure: F) -> PolarResult<QueryResults>
+where
+    F: FnOnce(&mut Query),
+{
+    let mut q = p.new_query(query_str, false)?;
+    configure(&mut q);
+    let mut error = None;
+    let results = query_results!(q, @errs |e| {
+        error = Some(e);
+        vec![]
+    });
+    error.map_or(Ok(results), Err)
+}
+
+/// An external instance with no constructor, repr or class.
+fn external_instance(instance_id: u64) -> Term {
+    term!(Value::ExternalInstance(ExternalInstance {
+        instance_id,
+        constructor: None,
+        repr: None,
+        class_repr: None,
+        class_id: None,
+    }))
+}
+
 #[track_caller]
 fn qeval(p: &Polar, query_str: &str) {
     assert!(eval(p, query_str));
@@ -502,6 +530,33 @@ fn test_trace() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_trace_rules() -> TestResult {
+    let p = polar();
+    p.load_str(
+        r#"f(x) if g(x);
+           g(1);
+           g(x) if x = 2;"#,
+    )?;
+    let q = p.new_query("f(2)", true)?;
+    let results = query_results!(q);
+    assert_eq!(results.len(), 1);
+    let rules = results[0].1.as_ref().unwrap().trace.rules();
+    let names = rules.iter().map(|r| r.name.0.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["f", "g"]);
+    if let SourceInfo::Parser(Context {
+        source,
+        left,
+        right,
+    }) = &rules[1].source_info
+    {
+        assert_eq!(&source.src[*left..*right], "g(x)");
+    } else {
+        panic!("expected rule to have parser source info");
+    }
+    Ok(())
+}
+
 #[test]
 fn test_nested_rule() -> TestResult {
     let p = polar();
@@ -630,6 +685,287 @@ fn test_multi_arg_method_ordering() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_load_facts() -> TestResult {
+    let p = polar();
+    p.load_str(r#"connected(a, b) if edge(a, b) or edge(b, a); edge(-1, -1);"#)?;
+    p.load_facts(
+        "edge",
+        (0..10_000).map(|i| vec![term!(i), term!(format!("n{}", i).as_str())]),
+    )?;
+
+    qvar(&p, "edge(1234, x)", "x", values!["n1234"]);
+    qvar(&p, r#"edge(x, "n9999")"#, "x", values![9999]);
+    qnull(&p, "edge(10000, _)");
+    qvar(&p, r#"connected("n42", x)"#, "x", values![42]);
+    Ok(())
+}
+
+#[test]
+fn test_load_facts_checks_rule_types() -> TestResult {
+    let p = polar();
+    p.load_str(
+        r#"type owner(user, doc);
+           owner("alice", 1);"#,
+    )?;
+    p.load_facts("owner", vec![vec![term!("bob"), term!(2)]])?;
+    qvar(&p, "owner(x, 2)", "x", values!["bob"]);
+
+    // A row that doesn't match the rule type is rejected, along with the
+    // rest of the batch.
+    let err = p
+        .load_facts(
+            "owner",
+            vec![
+                vec![term!("carol"), term!(3)],
+                vec![term!("dave"), term!(4), term!(5)],
+            ],
+        )
+        .unwrap_err();
+    assert!(
+        matches!(err.0, ErrorKind::Validation(InvalidRule { .. })),
+        "{}",
+        err
+    );
+    qnull(&p, "owner(_, 3)");
+    Ok(())
+}
+
+#[test]
+fn test_deny_facts() -> TestResult {
+    let p = polar();
+    p.load_str(
+        r#"allow(user, action, resource) if
+             grant(user, action, resource) and
+             not deny(user, action, resource);
+           grant(_, "read", _);
+           deny("mallory", "read", "secrets");"#,
+    )?;
+    p.load_facts(
+        "deny",
+        vec![vec![term!("eve"), term!("read"), term!("doc")]],
+    )?;
+
+    qeval(&p, r#"allow("alice", "read", "doc")"#);
+    qeval(&p, r#"allow("mallory", "read", "doc")"#);
+    qnull(&p, r#"allow("mallory", "read", "secrets")"#);
+    qnull(&p, r#"allow("eve", "read", "doc")"#);
+    qnull(&p, r#"allow("alice", "write", "doc")"#);
+    Ok(())
+}
+
+#[test]
+fn test_query_rule_with_native_args() -> TestResult {
+    let p = polar();
+    p.load_str(
+        r#"f(1, "a", true);
+           f(2, "b", false);
+           total([x, *rest], sum) if total(rest, s) and sum = x + s;
+           total([], 0);"#,
+    )?;
+
+    let q = p.query_rule(
+        "f",
+        vec![Term::from(1), Term::from("a"), Term::from(true)],
+        false,
+    );
+    assert_eq!(query_results!(q).len(), 1);
+    let q = p.query_rule(
+        "f",
+        vec![Term::from(1), Term::from("b"), Term::from(true)],
+        false,
+    );
+    assert!(query_results!(q).is_empty());
+
+    // Unbound arguments are bound in the results.
+    let q = p.query_rule(
+        "f",
+        vec![Term::from(2), Term::from(sym!("s")), Term::from(sym!("b"))],
+        false,
+    );
+    let results = query_results!(q);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0[&sym!("s")], value!("b"));
+    assert_eq!(results[0].0[&sym!("b")], value!(false));
+
+    // Vectors become lists.
+    let q = p.query_rule(
+        "total",
+        vec![Term::from(vec![1, 2, 3]), Term::from(6)],
+        false,
+    );
+    assert_eq!(query_results!(q).len(), 1);
+    assert_eq!(Term::from(vec!["a", "b"]).value(), &value!(["a", "b"]));
+    Ok(())
+}
+
+#[test]
+fn test_serialized_rules_and_query_round_trip() -> TestResult {
+    let p = polar();
+    p.load_str(
+        r#"total([x, *rest], sum) if total(rest, s) and sum = x + s;
+           total([], 0);
+           owner(_user: {name: name}, {owner: name});"#,
+    )?;
+    let rules = serde_json::to_string(&p.rules()).unwrap();
+    let query = polar_core::parser::parse_query(
+        r#"total([1, 2, 3], t) and owner({name: "sam"}, {owner: o})"#,
+    )?;
+    let query = serde_json::to_string(&query).unwrap();
+
+    // Rehydrate both into a fresh knowledge base without parsing.
+    let restored = polar();
+    restored.load_rules(serde_json::from_str(&rules).unwrap())?;
+    assert_eq!(restored.rules(), p.rules());
+    let term: Term = serde_json::from_str(&query).unwrap();
+    let results = query_results!(restored.new_query_from_term(term, false));
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0[&sym!("t")], value!(6));
+    assert_eq!(results[0].0[&sym!("o")], value!("sam"));
+
+    // Like `load`, loading rules into a knowledge base that has rules fails.
+    let err = restored.load_rules(p.rules()).unwrap_err();
+    assert!(matches!(err.0, ErrorKind::Runtime(MultipleLoadError)));
+
+    // Restored rules are validated, and the knowledge base is left empty if
+    // they are invalid.
+    let p = polar();
+    p.load_str("f(x) if g(x); g(1);")?;
+    let rules = p
+        .rules()
+        .into_iter()
+        .filter(|rule| rule.name.0 == "f")
+        .collect();
+    let restored = polar();
+    let err = restored.load_rules(rules).unwrap_err();
+    assert!(
+        matches!(err.0, ErrorKind::Validation(UndefinedRuleCall { .. })),
+        "{}",
+        err
+    );
+    assert!(restored.rules().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_query_sees_rules_from_when_it_started() -> TestResult {
+    use std::sync::Arc;
+
+    let p = Arc::new(polar());
+    p.load_str("f(1); f(2); f(3); g(x) if f(x);")?;
+
+    // Take the first result, then reload the policy on another thread
+    // before asking for the rest.
+    let mut q = p.new_query("x in [1, 2, 3] and g(x)", false)?;
+    let first = q
+        .find_map(|event| match event {
+            Ok(QueryEvent::Result { bindings, .. }) => Some(bindings[&sym!("x")].clone()),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(first, term!(1));
+
+    let reloader = p.clone();
+    std::thread::spawn(move || {
+        reloader.clear_rules();
+        reloader.load_str("f(10); g(x) if f(x) and x > 5;")
+    })
+    .join()
+    .unwrap()?;
+
+    // The running query keeps the rules it started with, including for
+    // the calls to `g` it makes after the reload.
+    let rest = query_results!(q)
+        .into_iter()
+        .map(|(bindings, _)| bindings[&sym!("x")].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(rest, values![2, 3]);
+
+    // New queries see the new rules.
+    qvar(&p, "g(x)", "x", values![10]);
+    Ok(())
+}
+
+#[test]
+fn test_query_sees_union_members_from_when_it_started() -> TestResult {
+    use std::sync::Arc;
+
+    let p = Arc::new(polar());
+    p.register_constant(sym!("User"), term!(true))?;
+    p.register_constant(sym!("Admin"), term!(true))?;
+    p.load_str("actor User {}")?;
+
+    // Answer every isa check, recording the class tag it asked about.
+    fn run_isas(q: &mut Query, tags: &mut Vec<String>, stop_after_result: bool) -> usize {
+        let mut results = 0;
+        loop {
+            match q.next_event().unwrap() {
+                QueryEvent::Done { .. } => return results,
+                QueryEvent::Result { .. } => {
+                    results += 1;
+                    if stop_after_result {
+                        return results;
+                    }
+                }
+                QueryEvent::ExternalIsa {
+                    call_id, class_tag, ..
+                } => {
+                    tags.push(class_tag.0);
+                    q.question_result(call_id, true).unwrap();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut q = p.new_query("x in [1, 2] and x matches Actor", false)?;
+    let mut tags = vec![];
+    assert_eq!(run_isas(&mut q, &mut tags, true), 1);
+    assert_eq!(tags, vec!["User"]);
+
+    let reloader = p.clone();
+    std::thread::spawn(move || reloader.reload(vec![Source::new("actor Admin {}")]))
+        .join()
+        .unwrap()?;
+
+    // The running query keeps the members `Actor` had when it started.
+    tags.clear();
+    assert_eq!(run_isas(&mut q, &mut tags, false), 1);
+    assert_eq!(tags, vec!["User"]);
+
+    // New queries see the new members.
+    let mut q = p.new_query("1 matches Actor", false)?;
+    tags.clear();
+    assert_eq!(run_isas(&mut q, &mut tags, false), 1);
+    assert_eq!(tags, vec!["Admin"]);
+    Ok(())
+}
+
+#[test]
+fn test_reload() -> TestResult {
+    let p = polar();
+    p.load_str("f(1); f(2);")?;
+    assert!(matches!(
+        p.load_str("f(3);").unwrap_err().0,
+        ErrorKind::Runtime(MultipleLoadError)
+    ));
+
+    p.reload(vec![Source::new("f(3);")])?;
+    qvar(&p, "f(x)", "x", values![3]);
+
+    // A failed reload keeps the old rules.
+    assert!(p.reload(vec![Source::new("f(4) if;")]).is_err());
+    assert!(p.reload(vec![Source::new("f(4); g(x) if h(x);")]).is_err());
+    qvar(&p, "f(x)", "x", values![3]);
+    assert_eq!(p.rules().len(), 1);
+
+    // Settings carry over to the reloaded policy.
+    p.set_dedup_rules(true);
+    p.reload(vec![Source::new("f(5); f(5);")])?;
+    qvar(&p, "f(x)", "x", values![5]);
+    Ok(())
+}
+
 #[test]
 fn test_no_applicable_rules() -> TestResult {
     let p = polar();
@@ -641,6 +977,108 @@ fn test_no_applicable_rules() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_empty_kb() {
+    let p = polar();
+    qruntime!(&p, "f(1)", QueryForUndefinedRule { name }, name == "f");
+    qruntime!(
+        &p,
+        "1 = 1 and f(1)",
+        QueryForUndefinedRule { name },
+        name == "f"
+    );
+
+    // Queries that don't call any rules don't need any.
+    qeval(&p, "1 = 1");
+    qnull(&p, "1 = 2");
+    qvar(&p, "x = [1, 2] and y in x", "y", values![1, 2]);
+}
+
+#[test]
+fn test_ground_query() -> TestResult {
+    // A query with no variables has exactly one empty result on success,
+    // and none on failure.
+    let p = polar();
+    p.load_str(
+        r#"allow("alice", "read", "doc");
+           allow(actor, "read", "doc") if actor = "bob" or actor = "bob";"#,
+    )?;
+    let results = query_results!(p.new_query(r#"allow("alice", "read", "doc")"#, false)?);
+    assert_eq!(results.len(), 1);
+    assert!(results[0].0.is_empty());
+    qnull(&p, r#"allow("alice", "write", "doc")"#);
+
+    // Each way of proving the query is still a separate result.
+    let results = query_results!(p.new_query(r#"allow("bob", "read", "doc")"#, false)?);
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|(bindings, _)| bindings.is_empty()));
+    Ok(())
+}
+
+#[test]
+fn test_undefined_rule_in_body() {
+    // Calls to undefined rules in rule bodies are caught when the policy is loaded,
+    // with the location of the call in the calling rule.
+    let e = polar().load_str("a() if 1 = 1 and b();").unwrap_err();
+    assert!(
+        matches!(&e.0, ErrorKind::Validation(UndefinedRuleCall { term }) if term.to_string() == "b()"),
+        "{}",
+        e
+    );
+    let msg = e.to_string();
+    assert!(msg.contains("Call to undefined rule: b()"), "{}", msg);
+    assert!(msg.contains("at line 1, column 18"), "{}", msg);
+    assert!(msg.contains("a() if 1 = 1 and b();"), "{}", msg);
+}
+
+#[test]
+fn test_load_and_query_errors() -> TestResult {
+    let p = polar();
+    assert!(p.load_str("f(x) if x = ;").unwrap_err().is_load_error());
+    assert!(p.load_str("f(x) if g(x);").unwrap_err().is_load_error());
+
+    p.load_str("f(x) if x < 1;")?;
+    assert!(p.load_str("g(1);").unwrap_err().is_load_error());
+
+    let e = _qruntime(&p, "g(1)");
+    assert!(
+        matches!(e.0, ErrorKind::Runtime(QueryForUndefinedRule { .. })),
+        "{}",
+        e
+    );
+    assert!(!e.is_load_error());
+    assert!(!_qruntime(&p, "f(\"a\")").is_load_error());
+    Ok(())
+}
+
+#[test]
+fn test_runtime_error_context() -> TestResult {
+    // Runtime errors point at the term in the policy that raised them.
+    let p = polar();
+    p.load_str("f(x) if\n    x < \"2\";\ng(x) if\n    x + 1 > 0;")?;
+
+    let e = _qruntime(&p, "f(1)");
+    assert!(
+        matches!(e.0, ErrorKind::Runtime(Unsupported { .. })),
+        "{}",
+        e
+    );
+    let msg = e.to_string();
+    assert!(msg.contains("at line 2, column 5"), "{}", msg);
+    assert!(msg.contains("002:     x < \"2\";"), "{}", msg);
+
+    let e = _qruntime(&p, "g(9223372036854775807)");
+    assert!(
+        matches!(e.0, ErrorKind::Runtime(ArithmeticError { .. })),
+        "{}",
+        e
+    );
+    let msg = e.to_string();
+    assert!(msg.contains("at line 4, column 5"), "{}", msg);
+    assert!(msg.contains("004:     x + 1 > 0;"), "{}", msg);
+    Ok(())
+}
+
 /// From Aït-Kaci's WAM tutorial (1999), page 34.
 #[test]
 fn test_ait_kaci_34() -> TestResult {
@@ -736,6 +1174,87 @@ fn test_not() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_deeply_nested_or() -> TestResult {
+    let p = polar();
+    let n = 10_000;
+    // x = 0 or (x = 1 or (x = 2 or ... (x = 9999 or false)...))
+    let src = (0..n)
+        .map(|i| format!("x = {} or (", i))
+        .collect::<String>()
+        + "false"
+        + &")".repeat(n);
+    let results = var(&p, &src, "x");
+    assert_eq!(results.len(), n);
+    assert_eq!(results[0], value!(0));
+    assert_eq!(results[n - 1], value!(n as i64 - 1));
+    Ok(())
+}
+
+#[test]
+fn test_not_discards_bindings() -> TestResult {
+    let p = polar();
+    qeval(&p, "not false");
+    qnull(&p, "not true");
+
+    // Negation as failure: `x = 1` succeeds for an unbound `x`, so its
+    // negation fails.
+    qnull(&p, "not x = 1");
+    qnull(&p, "not (x = 1 or x = 2)");
+
+    // Bindings made while evaluating the negated term never escape it.
+    let results = query_results!(p.new_query("not (x = 1 and x = 2)", false)?);
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].0.contains_key(&sym!("x")));
+    qvar(&p, "(not x = 1 or true) and x = 2", "x", values![2]);
+    Ok(())
+}
+
+#[test]
+fn test_backtracking_restores_bindings() -> TestResult {
+    // Every third branch binds its variables and then fails; backtracking
+    // out of it must undo those bindings before the next branch runs.
+    let p = polar();
+    let n = 90;
+    let policy = (0..n)
+        .map(|i| {
+            let fail = if i % 3 == 0 {
+                " and z = x and z = -1"
+            } else {
+                ""
+            };
+            format!(
+                "branch(x, y, {i}) if x = {i} and y = [x, {i}]{fail};",
+                i = i,
+                fail = fail
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    p.load_str(&policy)?;
+
+    let results = query_results!(p.new_query("branch(x, y, i)", false)?);
+    let expected = (0..n).filter(|i| i % 3 != 0).collect::<Vec<i64>>();
+    assert_eq!(results.len(), expected.len());
+    for ((bindings, _), i) in results.iter().zip(expected.iter()) {
+        let mut vars = bindings.keys().map(|k| k.0.as_str()).collect::<Vec<_>>();
+        vars.sort_unstable();
+        assert_eq!(vars, vec!["i", "x", "y"]);
+        assert_eq!(bindings[&sym!("i")], value!(*i));
+        assert_eq!(bindings[&sym!("x")], value!(*i));
+        assert_eq!(bindings[&sym!("y")], value!([*i, *i]));
+    }
+
+    // Nesting the choice points: the inner call sees only the outer
+    // branch's binding for `x`, never one left over from a failed branch.
+    let results = query_results!(p.new_query("branch(x, _, i) and branch(x, _, j)", false)?);
+    assert_eq!(results.len(), expected.len());
+    for (bindings, _) in results.iter() {
+        assert_eq!(bindings[&sym!("i")], bindings[&sym!("j")]);
+    }
+    Ok(())
+}
+
 #[test]
 fn test_and() -> TestResult {
     let p = polar();
@@ -748,16 +1267,91 @@ fn test_and() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_output_param_determined_by_body() -> TestResult {
+    let p = polar();
+    p.load_str(
+        r#"max(a, b, a) if a >= b;
+           max(a, b, b) if b > a;"#,
+    )?;
+    qvar(&p, "max(3, 5, m)", "m", values![5]);
+    qvar(&p, "max(5, 3, m)", "m", values![5]);
+    qvar(&p, "max(4, 4, m)", "m", values![4]);
+    qeval(&p, "max(3, 5, 5)");
+    qnull(&p, "max(3, 5, 3)");
+    qvar(&p, "max(1, 2, m) and max(m, 0, n)", "n", values![2]);
+    Ok(())
+}
+
+#[test]
+fn test_head_var_bound_in_body() -> TestResult {
+    let p = polar();
+    p.load_str(
+        r#"f(x) if x = 1;
+           g(x, y) if y = x and x = 2;"#,
+    )?;
+
+    // The caller's variable is bound through the head variable, which the
+    // body binds.
+    qvar(&p, "f(y)", "y", values![1]);
+    qvar(&p, "g(a, b)", "a", values![2]);
+    qvar(&p, "g(a, b)", "b", values![2]);
+    qvar(&p, "f(x)", "x", values![1]);
+    qnull(&p, "y = 2 and f(y)");
+    Ok(())
+}
+
+#[test]
+fn test_rule_body_variables_are_local() -> TestResult {
+    let p = polar();
+    p.load_str("step(out) if tmp = 1 and out = tmp + 1;")?;
+
+    // A rule body is a scope: its variables are renamed on each call, so
+    // only the head parameters escape to the caller.
+    qvar(&p, "tmp = 5 and step(x)", "tmp", values![5]);
+    qvar(&p, "tmp = 5 and step(x)", "x", values![2]);
+    qvar(&p, "step(x) and step(y) and z = x + y", "z", values![4]);
+    let results = query_results!(p.new_query("step(x)", false)?);
+    assert_eq!(results.len(), 1);
+    assert_eq!(
+        results[0].0.keys().cloned().collect::<HashSet<_>>(),
+        hashset! {sym!("x")}
+    );
+    Ok(())
+}
+
 #[test]
 fn test_equality() {
     let p = polar();
     qeval(&p, "1 = 1");
     qnull(&p, "1 = 2");
+
+    // Numbers and strings never unify, whichever side they're on.
+    qnull(&p, r#"1 = "1""#);
+    qnull(&p, r#""1" = 1"#);
+    qnull(&p, r#"1.0 = "1.0""#);
+    qnull(&p, r#"x = 1 and x = "1""#);
+    qnull(&p, r#"x = "1" and 1 = x"#);
+    qnull(&p, r#"[1] = ["1"]"#);
+    qnull(&p, r#"{a: 1} = {a: "1"}"#);
+    qnull(&p, r#"true = "true""#);
+
+    // Comparing them is an error rather than a coercion.
+    qruntime!(&p, r#"1 == "1""#, Unsupported { .. });
+    qruntime!(&p, r#"1 != "1""#, Unsupported { .. });
 }
 
 #[test]
 fn test_lookup() {
-    qeval(&polar(), "{x: 1}.x = 1");
+    let p = polar();
+    qeval(&p, "{x: 1}.x = 1");
+    qeval(&p, "d = {a: 1} and d.a = 1");
+    qnull(&p, "d = {a: 1} and d.a = 2");
+    qvar(&p, "d = {a: {b: 2}} and d.a.b = x", "x", values![2]);
+
+    // A missing key fails rather than erroring.
+    qnull(&p, "d = {a: 1} and d.missing = x");
+    qeval(&p, "d = {a: 1} and not d.missing = _");
 }
 
 #[test]
@@ -869,6 +1463,12 @@ fn test_or() -> TestResult {
     qnull(&p, "g(2)");
     qeval(&p, "g(3)");
     qeval(&p, "g(5)");
+
+    // Each branch starts from the bindings in effect before the `or`, and
+    // all solutions of the left branch come before those of the right.
+    qvar(&p, "x = 1 or x = 2", "x", values![1, 2]);
+    qvar(&p, "(g(x) and x > 1) or x = 0", "x", values![3, 5, 0]);
+    qvar(&p, "y = 1 and (x = y or x = y + 1)", "x", values![1, 2]);
     Ok(())
 }
 
@@ -1017,6 +1617,236 @@ fn test_bindings() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_raw_bindings() -> TestResult {
+    let p = polar();
+    let run = |src, raw| query_with(&p, src, |q| q.set_raw_bindings(raw));
+
+    let resolved = run("x = [y] and y = 1", false)?;
+    assert_eq!(resolved[0].0[&sym!("x")], value!([1]));
+
+    let raw = run("x = [y] and y = 1", true)?;
+    assert_eq!(raw[0].0[&sym!("x")], value!([sym!("y")]));
+    assert_eq!(raw[0].0[&sym!("y")], value!(1));
+
+    // Constraints are returned as expressions.
+    let raw = run("x > 1", true)?;
+    assert!(matches!(raw[0].0[&sym!("x")], Value::Expression(_)));
+    Ok(())
+}
+
+#[test]
+fn test_backtrack_observer() -> TestResult {
+    use std::rc::Rc;
+
+    let p = polar();
+    p.load_str("f(1); f(2); f(3); g(x) if f(x) and x > 2;")?;
+    let failures = |src: &str| -> PolarResult<(usize, Vec<String>)> {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let observed = seen.clone();
+        let mut q = p.new_query(src, false)?;
+        q.set_backtrack_observer(move |term| observed.borrow_mut().push(term.to_string()));
+        let results = query_results!(q).len();
+        let seen = seen.borrow().clone();
+        Ok((results, seen))
+    };
+
+    // Each failed branch is reported with the term that failed.
+    assert_eq!(
+        failures("x in [1, 2, 3] and x = 3")?,
+        (1, vec!["x = 3".to_owned(), "x = 3".to_owned()])
+    );
+    assert_eq!(
+        failures("g(x)")?,
+        (1, vec!["1 > 2".to_owned(), "2 > 2".to_owned()])
+    );
+
+    // Rule selection and moving on to the next result aren't failures.
+    assert_eq!(failures("f(x)")?, (3, vec![]));
+    assert_eq!(failures("f(4)")?, (0, vec!["f(4)".to_owned()]));
+    Ok(())
+}
+
+#[test]
+fn test_progress_observer() -> TestResult {
+    use std::rc::Rc;
+
+    let p = polar();
+    p.load_str(
+        r#"even(x) if x mod 2 = 0;
+           g(x) if x in [1, 2, 3, 4, 5, 6] and even(x);"#,
+    )?;
+    let seen = Rc::new(RefCell::new(vec![]));
+    let observed = seen.clone();
+    let mut q = p.new_query("g(x)", false)?;
+    q.set_progress_observer(3, move |progress| observed.borrow_mut().push(*progress));
+    assert_eq!(query_results!(q).len(), 3);
+
+    // Reported every third choice point, with counts that only grow as
+    // the query finds its results.
+    let seen = seen.borrow();
+    assert!(seen.len() > 2);
+    for (i, progress) in seen.iter().enumerate() {
+        assert_eq!(progress.choice_points, 3 * (i + 1));
+    }
+    for pair in seen.windows(2) {
+        assert!(pair[0].rules_applied <= pair[1].rules_applied);
+        assert!(pair[0].results <= pair[1].results);
+    }
+    assert_eq!(seen[0].results, 0);
+    let last = seen.last().unwrap();
+    assert!(last.results >= 2 && last.rules_applied >= 5);
+    Ok(())
+}
+
+#[test]
+fn test_rule_mismatch_observer() -> TestResult {
+    use polar_core::rules::RuleMismatchReason;
+    use std::rc::Rc;
+
+    let p = polar();
+    p.register_constant(sym!("User"), term!(true))?;
+    p.register_constant(sym!("Robot"), term!(true))?;
+    p.load_str(
+        r#"greet(_: User, {text: msg}) if msg = "hello";
+           greet(_: Robot, {text: msg}) if msg = "beep";"#,
+    )?;
+    let mismatches = |src: &str| -> PolarResult<(usize, Vec<String>)> {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let observed = seen.clone();
+        let mut q = p.new_query(src, false)?;
+        q.set_rule_mismatch_observer(move |mismatch| {
+            // Name each rule by its first parameter's specializer.
+            let rule = mismatch.rule.params[0].specializer.as_ref().unwrap();
+            let reason = match &mismatch.reason {
+                RuleMismatchReason::Unify { arg, param } => format!("{} != {}", arg, param),
+                RuleMismatchReason::Isa { arg, specializer } => {
+                    assert!(matches!(arg.value(), Value::ExternalInstance(_)));
+                    format!("not {}", specializer)
+                }
+            };
+            observed.borrow_mut().push(format!("{}: {}", rule, reason));
+        });
+        let (results, _externals) = query_results_with_externals(q);
+        let seen = seen.borrow().clone();
+        Ok((results.len(), seen))
+    };
+
+    // The class specializer of the `Robot` rule doesn't match the instance.
+    assert_eq!(
+        mismatches(r#"greet(new User(name: "sam"), {text: x})"#)?,
+        (1, vec!["Robot{}: not Robot{}".to_owned()])
+    );
+
+    // Checks run in parameter order, and only the first failure is reported.
+    assert_eq!(
+        mismatches(r#"greet(new User(name: "sam"), "hello")"#)?,
+        (
+            0,
+            vec![
+                "Robot{}: not Robot{}".to_owned(),
+                r#"User{}: "hello" != {text: msg}"#.to_owned()
+            ]
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn test_constraint_results() -> TestResult {
+    let p = polar();
+    p.load_str("big(x) if x > 5;")?;
+
+    // Unbound but constrained variables come back as their constraints,
+    // with `_this` standing in for the variable itself.
+    let constraint = |q| {
+        let x = var(&p, q, "x").remove(0);
+        assert!(matches!(x, Value::Expression(_)));
+        term!(x).to_string()
+    };
+    assert_eq!(constraint("x > 5"), "_this > 5");
+    assert_eq!(constraint("big(x)"), "_this > 5");
+    assert_eq!(constraint("big(x) and x != 7"), "_this > 5 and _this != 7");
+
+    // Once bound, the variable's value is returned instead.
+    qvar(&p, "big(x) and x = 6", "x", values![6]);
+    Ok(())
+}
+
+#[test]
+fn test_recursive_rules() -> TestResult {
+    let p = polar();
+    p.load_str(
+        r#"parent("abe", "homer");
+           parent("homer", "bart");
+           parent("homer", "lisa");
+           parent("homer", "maggie");
+           parent("mona", "homer");
+           ancestor(x, y) if parent(x, y);
+           ancestor(x, y) if parent(x, z) and ancestor(z, y);"#,
+    )?;
+    let mut ancestors = var(&p, r#"ancestor(x, "lisa")"#, "x");
+    ancestors.sort_by_key(|v| v.to_string());
+    assert_eq!(ancestors, values!["abe", "homer", "mona"]);
+
+    let descendants = var(&p, r#"ancestor("abe", x)"#, "x");
+    assert_eq!(descendants, values!["homer", "bart", "lisa", "maggie"]);
+    qnull(&p, r#"ancestor("bart", x)"#);
+
+    // There's no tabling, so recursion over cyclic data must track where
+    // it's been to terminate.
+    p.clear_rules();
+    p.load_str(
+        r#"edge(1, 2);
+           edge(2, 3);
+           edge(3, 1);
+           reachable(x, y) if reachable(x, y, [x]);
+           reachable(x, y, _) if edge(x, y);
+           reachable(x, y, seen) if
+               edge(x, z) and not z in seen and reachable(z, y, [z, *seen]);"#,
+    )?;
+    let mut reachable = var(&p, "reachable(1, x)", "x");
+    reachable.sort_by_key(|v| v.to_string());
+    reachable.dedup();
+    assert_eq!(reachable, values![1, 2, 3]);
+    Ok(())
+}
+
+#[test]
+fn test_results_are_fully_resolved() -> TestResult {
+    // Variables bound inside a rule body are resolved all the way down,
+    // including inside lists and dictionaries, before reaching the caller.
+    let p = polar();
+    p.load_str(
+        r#"f(out) if out = [1, 2, a] and a = 3;
+           g(out) if out = {x: [a, {y: b}]} and a = 1 and b = 2;"#,
+    )?;
+    qvar(&p, "f(out)", "out", vec![value!([1, 2, 3])]);
+    qvar(
+        &p,
+        "g(out)",
+        "out",
+        vec![Value::Dictionary(Dictionary {
+            fields: btreemap! {
+                sym!("x") => term!([1, btreemap! {sym!("y") => term!(2)}])
+            },
+        })],
+    );
+    Ok(())
+}
+
+#[test]
+fn test_list_index() {
+    let p = polar();
+    qeval(&p, "[1, 2, 3].(0) = 1");
+    qeval(&p, "[1, 2, 3].(-1) = 3");
+    qvar(&p, "x = [1, 2, 3] and y = x.(-3)", "y", values![1]);
+    qeval(&p, "i = 1 and [[1, 2], [3, 4]].(i).(-2) = 3");
+    qnull(&p, "[1, 2, 3].(3) = x");
+    qnull(&p, "[1, 2, 3].(-4) = x");
+    qnull(&p, "[].(0) = x");
+}
+
 #[test]
 fn test_lookup_derefs() -> TestResult {
     let p = polar();
@@ -1094,27 +1924,320 @@ fn test_make_external() -> TestResult {
     Ok(())
 }
 
-/// Test external call with positional + kwargs.
+/// Test external call with positional + kwargs.
+#[test]
+fn test_external_call() -> TestResult {
+    let p = polar();
+    p.register_constant(sym!("Foo"), term!(true))?;
+    let mut foo_lookups = vec![term!(1)];
+
+    let q = p.new_query("(new Foo()).bar(1, a: 2, b: 3) = 1", false)?;
+
+    let mock_foo_lookup =
+        |_, _, _, args: Option<Vec<Term>>, kwargs: Option<BTreeMap<Symbol, Term>>| {
+            assert_eq!(args.unwrap()[0], term!(1));
+            assert_eq!(
+                kwargs.unwrap(),
+                btreemap! {sym!("a") => term!(2), sym!("b") => term!(3)}
+            );
+            foo_lookups.pop()
+        };
+    let results = query_results!(q, mock_foo_lookup);
+    assert_eq!(results.len(), 1);
+    assert!(foo_lookups.is_empty());
+    Ok(())
+}
+
+/// Test that external instances in results are returned as-is, without calling into the host.
+#[test]
+fn test_external_instance_in_results() -> TestResult {
+    let p = polar();
+    let foo = external_instance(1);
+    p.register_constant(sym!("foo"), foo.clone())?;
+
+    let q = p.new_query("y = foo and x = [{a: y}, y]", false)?;
+    let panic_on_call = |_, _, _, _, _| panic!("unexpected external call");
+    let panic_on_make = |_, _| panic!("unexpected make external");
+    let results = query_results!(q, panic_on_call, panic_on_make, no_debug);
+    assert_eq!(results.len(), 1);
+    assert_eq!(&results[0].0[&sym!("y")], foo.value());
+    assert_eq!(
+        results[0].0[&sym!("x")],
+        Value::List(vec![term!(btreemap! {sym!("a") => foo.clone()}), foo])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_forbid_calls_in_negation() -> TestResult {
+    let p = polar();
+    p.register_constant(sym!("foo"), external_instance(1))?;
+
+    let mut calls = vec![];
+    {
+        let mut run = |src: &str, forbid: bool| -> PolarResult<usize> {
+            let mut q = p.new_query(src, false)?;
+            q.set_forbid_calls_in_negation(forbid);
+            let mut error = None;
+            let results = query_results(
+                q,
+                |_, _, attr: Symbol, _, _| {
+                    calls.push(attr.0);
+                    Some(term!("alice"))
+                },
+                no_externals,
+                no_isa,
+                no_is_subspecializer,
+                no_debug,
+                print_messages,
+                |e| {
+                    error = Some(e);
+                    vec![]
+                },
+            );
+            error.map_or(Ok(results.len()), Err)
+        };
+
+        // By default, methods can be called inside a negation.
+        assert_eq!(run("not foo.delete() = \"bob\"", false)?, 1);
+        assert!(matches!(
+            run("not foo.delete() = \"bob\"", true),
+            Err(PolarError(ErrorKind::Runtime(Unsupported { .. })))
+        ));
+        assert!(matches!(
+            run("forall(x in [1], foo.delete(x) = \"alice\")", true),
+            Err(PolarError(ErrorKind::Runtime(Unsupported { .. })))
+        ));
+
+        // Attribute lookups and calls outside of a negation are still allowed.
+        assert_eq!(run("not foo.name = \"bob\"", true)?, 1);
+        assert_eq!(run("foo.delete() = \"alice\"", true)?, 1);
+    }
+    assert_eq!(calls, vec!["delete", "name", "delete"]);
+    Ok(())
+}
+
+#[test]
+fn test_unbound_host_method_argument() -> TestResult {
+    let p = polar();
+    p.register_constant(sym!("user"), external_instance(1))?;
+    p.load_str("has(u, role) if u.has_role(role);")?;
+
+    qruntime!(
+        &p,
+        "user.has_role(r)",
+        TypeError { msg, .. },
+        msg == "host method has_role called with unbound argument r"
+    );
+    qruntime!(
+        &p,
+        "user.has_role(kind: r)",
+        TypeError { msg, .. },
+        msg.ends_with("unbound argument r")
+    );
+    qruntime!(
+        &p,
+        "has(user, _)",
+        TypeError { msg, .. },
+        msg.starts_with("host method has_role called with unbound argument")
+    );
+
+    // Bound arguments are passed through to the host.
+    let mut args = vec![];
+    let results = query_results(
+        p.new_query(r#"has(user, "admin")"#, false)?,
+        |_, _, _, a: Option<Vec<Term>>, _| {
+            args.push(a.unwrap());
+            Some(term!(true))
+        },
+        no_externals,
+        no_isa,
+        no_is_subspecializer,
+        no_debug,
+        print_messages,
+        |e| panic!("{}", e),
+    );
+    assert_eq!(results.len(), 1);
+    assert_eq!(args, vec![vec![term!("admin")]]);
+    Ok(())
+}
+
+#[test]
+fn test_result_instance_ids() -> TestResult {
+    let p = polar();
+    p.register_constant(sym!("a"), external_instance(1))?;
+    p.register_constant(sym!("b"), external_instance(2))?;
+    p.register_constant(sym!("c"), external_instance(3))?;
+
+    let q = p.new_query("x = [a, {b: b, c: [c, a]}] and y = 1", false)?;
+    let results = query_results!(q);
+    assert_eq!(results.len(), 1);
+    let mut ids = HashSet::new();
+    for value in results[0].0.values() {
+        term!(value.clone()).instance_ids(&mut ids);
+    }
+    assert_eq!(ids, hashset! {1, 2, 3});
+    Ok(())
+}
+
+/// Test that conjunctions are evaluated lazily: pulling one result only
+/// evaluates the last conjunct once.
+#[test]
+fn test_conjunction_is_lazy() -> TestResult {
+    let p = polar();
+    p.register_constant(sym!("foo"), external_instance(1))?;
+
+    let mut q = p.new_query("x in [1, 2, 3] and foo.expensive(x) = y", false)?;
+    let mut calls = 0;
+    loop {
+        match q.next_event()? {
+            QueryEvent::ExternalCall { call_id, args, .. } => {
+                calls += 1;
+                let value = args.unwrap().pop();
+                q.call_result(call_id, value)?;
+            }
+            QueryEvent::Result { bindings, .. } => {
+                assert_eq!(bindings[&sym!("y")], term!(1));
+                break;
+            }
+            QueryEvent::Done { .. } => panic!("expected a result"),
+            _ => {}
+        }
+    }
+    assert_eq!(calls, 1);
+    Ok(())
+}
+
+/// Test that a query can be paused on an external call and resumed later,
+/// e.g. after the host has awaited the result asynchronously.
+#[test]
+fn test_pause_and_resume_on_external_call() -> TestResult {
+    let p = polar();
+    p.register_constant(sym!("db"), external_instance(1))?;
+    p.load_str("owner(doc, user) if db.owner_of(doc) = user;")?;
+
+    let mut paused = p.new_query("owner(1, user)", false)?;
+    let call_id = match paused.next_event()? {
+        QueryEvent::ExternalCall {
+            call_id, attribute, ..
+        } => {
+            assert_eq!(attribute, sym!("owner_of"));
+            call_id
+        }
+        e => panic!("expected external call, got {:?}", e),
+    };
+
+    // Other queries can run to completion while the first one waits.
+    qeval(&p, "1 = 1");
+
+    paused.call_result(call_id, Some(term!("alice")))?;
+    let results = query_results!(paused);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0[&sym!("user")], value!("alice"));
+    Ok(())
+}
+
+#[test]
+fn test_stack_limit() -> TestResult {
+    let p = polar();
+    p.load_str(
+        r#"loop(x) if loop(x);
+           count(0);
+           count(n) if n > 0 and count(n - 1);"#,
+    )?;
+
+    let run = |src| query_with(&p, src, |q| q.set_stack_limit(100));
+
+    let err = run("loop(1)").unwrap_err();
+    assert!(
+        matches!(&err.0, ErrorKind::Runtime(StackOverflow { msg }) if msg.contains("MAX_GOALS = 100")),
+        "{}",
+        err
+    );
+
+    // The limit applies inside negation too.
+    let err = run("not loop(1)").unwrap_err();
+    assert!(err.to_string().contains("MAX_GOALS = 100"), "{}", err);
+
+    // Shallow recursion stays under the limit.
+    assert_eq!(run("count(5)")?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_max_term_size() -> TestResult {
+    let p = polar();
+    p.load_str("grow(x) if grow([x]);")?;
+
+    let run = |src| query_with(&p, src, |q| q.set_max_term_size(Some(100)));
+
+    let err = run("grow(1)").unwrap_err();
+    assert!(
+        matches!(err.0, ErrorKind::Runtime(TermSizeExceeded { max: 100 })),
+        "{}",
+        err
+    );
+
+    // Small and cyclic terms are fine.
+    assert_eq!(run("x = [1, [2, [3]], {a: 4}]")?.len(), 1);
+    assert_eq!(run("x = [x]")?.len(), 1);
+    Ok(())
+}
+
 #[test]
-fn test_external_call() -> TestResult {
+fn test_max_applicable_rules() -> TestResult {
     let p = polar();
-    p.register_constant(sym!("Foo"), term!(true))?;
-    let mut foo_lookups = vec![term!(1)];
+    let facts = (0..200).map(|i| format!("n({});", i)).collect::<String>();
+    p.load_str(&facts)?;
 
-    let q = p.new_query("(new Foo()).bar(1, a: 2, b: 3) = 1", false)?;
+    let run = |src| query_with(&p, src, |q| q.set_max_applicable_rules(Some(100)));
 
-    let mock_foo_lookup =
-        |_, _, _, args: Option<Vec<Term>>, kwargs: Option<BTreeMap<Symbol, Term>>| {
-            assert_eq!(args.unwrap()[0], term!(1));
-            assert_eq!(
-                kwargs.unwrap(),
-                btreemap! {sym!("a") => term!(2), sym!("b") => term!(3)}
-            );
-            foo_lookups.pop()
-        };
-    let results = query_results!(q, mock_foo_lookup);
-    assert_eq!(results.len(), 1);
-    assert!(foo_lookups.is_empty());
+    let err = run("n(x)").unwrap_err();
+    assert!(
+        matches!(&err.0, ErrorKind::Runtime(TooManyApplicableRules { name, count: 200, max: 100 }) if name == "n"),
+        "{}",
+        err
+    );
+
+    // Calls whose arguments rule out most of the rules are fine.
+    assert_eq!(run("n(5)")?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_allowed_rules() -> TestResult {
+    let p = polar();
+    p.load_str(
+        r#"can_enter(u) if is_admin(u);
+           is_admin("root");
+           secret(42);"#,
+    )?;
+
+    let run = |src, allowed: &[&str]| {
+        let allowed = allowed.iter().map(|name| sym!(name)).collect();
+        query_with(&p, src, |q| q.set_allowed_rules(Some(allowed)))
+    };
+
+    assert_eq!(run("can_enter(u)", &["can_enter", "is_admin"])?.len(), 1);
+
+    let err = run("secret(x)", &["can_enter", "is_admin"]).unwrap_err();
+    assert!(
+        matches!(&err.0, ErrorKind::Runtime(QueryForDisallowedRule { name }) if name == "secret"),
+        "{}",
+        err
+    );
+    assert_eq!(
+        err.to_string(),
+        "Query for rule `secret`, which is not allowed"
+    );
+
+    // Rules called from other rules are checked too.
+    let err = run("can_enter(u)", &["can_enter"]).unwrap_err();
+    assert!(
+        matches!(&err.0, ErrorKind::Runtime(QueryForDisallowedRule { name }) if name == "is_admin"),
+        "{}",
+        err
+    );
     Ok(())
 }
 
@@ -1237,6 +2360,115 @@ fn test_comparisons() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_comparison_operands() -> TestResult {
+    let p = polar();
+
+    // Integers are promoted to floats when compared with them.
+    qeval(&p, "1 < 1.5 and 2.5 > 2 and 2 >= 2.0 and 2.0 <= 2");
+    qnull(&p, "2 != 2.0");
+    qnull(&p, "1.5 < 1");
+
+    // Comparing an unbound variable doesn't fail or error; it constrains the
+    // variable, and the constraint is checked once the variable is bound.
+    qeval(&p, "x > 5 and x = 6");
+    qnull(&p, "x > 5 and x = 4");
+    qnull(&p, "x >= 1.5 and x = 1");
+
+    // Arithmetic operands are evaluated before comparing.
+    qeval(&p, "x = 5 and y = 3 and x < y + 3");
+    qnull(&p, "x = 5 and y = 3 and x < y + 1");
+    qeval(&p, "x = 5 and y = 3 and x - 1 >= y * 2 - 2");
+    qeval(&p, "x = 5 and y = 3 and (x + y) / 2 == 4");
+
+    // A chain of comparisons in a conjunction shares bindings made by
+    // earlier goals.
+    qeval(&p, "b = 5 and a = 3 and a < b and b < 10");
+    qnull(&p, "b = 5 and a = 3 and a < b and b < 5");
+    qnull(&p, "a < b and b < 10 and b = 5 and a = 7");
+    qvar(&p, "a < b and b < 10 and b = 5 and a = 3", "a", values![3]);
+
+    // Comparing values that have no ordering is an error.
+    qruntime!(&p, r#"1 < "a""#, Unsupported { .. });
+    qruntime!(&p, "[1] < [2]", Unsupported { .. });
+    Ok(())
+}
+
+#[test]
+fn test_list_equality() {
+    let p = polar();
+    qeval(&p, "[] == []");
+    qeval(&p, "[1, 2] == [1, 2]");
+    qeval(&p, "[1, 2.0] == [1.0, 2]");
+    qeval(&p, "[[1], \"a\"] == [[1.0], \"a\"]");
+    qnull(&p, "[1, 2] == [2, 1]");
+    qnull(&p, "[1, 2] != [1.0, 2.0]");
+    qeval(&p, "[1, 2] != [2, 1]");
+    qeval(&p, "[1, 2.0] = [1.0, 2]");
+    qeval(&p, "x = [1, 2] and x == [1.0, 2.0]");
+
+    // Lists of different lengths are never equal, even if one is a prefix
+    // of the other.
+    qnull(&p, "[1, 2] == [1, 2, 3]");
+    qnull(&p, "[1, 2, 3] == [1, 2]");
+    qnull(&p, "[] == [1]");
+    qeval(&p, "[1, 2] != [1, 2, 3]");
+    qnull(&p, "[1, 2] = [1, 2, 3]");
+    qnull(&p, "x = [1, 2] and y = [1, 2, 3] and x == y");
+    qnull(&p, "[[1, 2]] == [[1, 2, 3]]");
+}
+
+#[test]
+fn test_dict_equality() {
+    let p = polar();
+    qeval(&p, "{} == {}");
+    qeval(&p, "{a: 1, b: 2} == {b: 2, a: 1}");
+    qeval(&p, "{a: 1, b: 2} = {b: 2, a: 1}");
+    qeval(&p, "{a: 1} == {a: 1.0}");
+    qeval(&p, "{a: [1, {b: 2}]} == {a: [1.0, {b: 2}]}");
+    qnull(&p, "{a: 1} == {a: 2}");
+    qnull(&p, "{a: 1} == {b: 1}");
+    qnull(&p, "{a: 1} == {a: 1, b: 2}");
+    qeval(&p, "{a: 1} != {a: 2}");
+    qnull(&p, "{a: 1, b: 2} != {b: 2, a: 1}");
+    qeval(&p, "x = {a: 1, b: 2} and x == {b: 2.0, a: 1.0}");
+}
+
+#[test]
+fn test_unify_empty_collections() {
+    let p = polar();
+    qeval(&p, "[] = []");
+    qeval(&p, "{} = {}");
+    qnull(&p, "[] = [1]");
+    qnull(&p, "[1] = []");
+    qnull(&p, "{} = {a: 1}");
+    qnull(&p, "{a: 1} = {}");
+    qnull(&p, "[] = {}");
+    qeval(&p, "x = [] and y = [] and x = y");
+    qnull(&p, "x = {} and x = {a: 1}");
+    qvar(&p, "[*rest] = []", "rest", vec![value!([])]);
+    qnull(&p, "[_x, *_rest] = []");
+}
+
+#[test]
+fn test_bind_constrained_variable() -> TestResult {
+    let p = polar();
+    qeval(&p, "x != 1 and x != 2 and x = 3");
+    qnull(&p, "x != 1 and x != 2 and x = 2");
+    qeval(&p, "x > 1 and x < 5 and x = 3");
+    qnull(&p, "x > 1 and x < 5 and x = 7");
+    qnull(&p, "x != 1 and x > 0 and x = 1");
+    qnull(&p, "x != 1 and y != 2 and x = y and y = 2");
+
+    // Constraints added inside a rule body still apply once the
+    // variable is bound by the caller.
+    p.load_str("small(x) if x > 0 and x < 10 and x != 5;")?;
+    qvar(&p, "small(x) and x = 3", "x", values![3]);
+    qnull(&p, "small(x) and x = 5");
+    qnull(&p, "small(x) and x = 12");
+    Ok(())
+}
+
 #[test]
 fn test_modulo_and_remainder() {
     let p = polar();
@@ -1321,6 +2553,144 @@ fn test_arithmetic() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_mutual_recursion() -> TestResult {
+    // Without cuts, the `n > 0` guards are what make these terminate.
+    let p = polar();
+    p.load_str(
+        r#"even(0);
+           even(n) if n > 0 and odd(n - 1);
+           odd(n) if n > 0 and even(n - 1);"#,
+    )?;
+
+    qeval(&p, "even(10)");
+    qeval(&p, "odd(7)");
+    qnull(&p, "even(7)");
+    qnull(&p, "odd(10)");
+    qnull(&p, "odd(0)");
+
+    // Each call explores a single chain of calls, so deep recursion stays
+    // linear and yields exactly one result.
+    let results = query_results!(p.new_query("even(200)", false)?);
+    assert_eq!(results.len(), 1);
+    qnull(&p, "odd(200)");
+    Ok(())
+}
+
+#[test]
+fn test_alias_chains_across_rule_frames() -> TestResult {
+    // Each call aliases its caller's variables to fresh rule variables, and
+    // the bodies alias them back to each other in both directions, so the
+    // chains run through several frames and close into cycles.
+    let p = polar();
+    p.load_str(
+        r#"same(x, y) if x = y and y = x;
+           swap(a, b) if same(b, a);
+           ring(p, q, r) if swap(p, q) and swap(q, r) and same(r, p);
+           nest(0, x, y) if same(x, y);
+           nest(n, x, y) if n > 0 and nest(n - 1, y, z) and swap(z, x);"#,
+    )?;
+
+    qeval(&p, "same(x, y) and same(y, x) and x = y");
+    qvar(&p, "ring(a, b, c) and c = 1", "a", values![1]);
+    qvar(&p, "ring(a, b, c) and b = 2", "c", values![2]);
+    qnull(&p, "ring(a, b, c) and a = 1 and c = 2");
+    qvar(&p, "a = 3 and ring(a, a, c)", "c", values![3]);
+    qvar(&p, "nest(20, x, y) and y = 4", "x", values![4]);
+    qnull(&p, "nest(20, x, y) and x = 4 and y = 5");
+    Ok(())
+}
+
+#[test]
+fn test_strict_float_arithmetic() -> TestResult {
+    let p = polar();
+
+    // By default, non-finite results are returned as floats.
+    qvar(&p, "x = 1.0 / 0.0", "x", values![f64::INFINITY]);
+
+    let strict_query = |src| query_with(&p, src, |q| q.set_strict_float_arithmetic(true));
+    assert!(matches!(
+        strict_query("x = 1.0 / 0.0"),
+        Err(PolarError(ErrorKind::Runtime(ArithmeticError { .. })))
+    ));
+    assert!(matches!(
+        strict_query("x = 1e308 * 10"),
+        Err(PolarError(ErrorKind::Runtime(ArithmeticError { .. })))
+    ));
+    assert!(matches!(
+        strict_query("not x = 1 / 0"),
+        Err(PolarError(ErrorKind::Runtime(ArithmeticError { .. })))
+    ));
+    assert_eq!(strict_query("x = 1.0 / 2.0")?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_string_concatenation() -> TestResult {
+    let p = polar();
+    qvar(&p, r#"x = "foo" + "bar""#, "x", values!["foobar"]);
+    qeval(&p, r#""a" + "b" + "c" == "abc""#);
+    qeval(&p, r#""" + "" = """#);
+    qnull(&p, r#""foo" + "bar" = "foo""#);
+
+    p.load_str(r#"greeting(name, g) if g = "hello, " + name;"#)?;
+    qvar(&p, r#"greeting("alice", g)"#, "g", values!["hello, alice"]);
+
+    // Only `+` works on strings, and only on two strings.
+    qruntime!(r#""a" - "b" = _"#, Unsupported { .. });
+    qruntime!(r#""a" + 1 = _"#, Unsupported { .. });
+    Ok(())
+}
+
+#[test]
+fn test_nested_arithmetic() -> TestResult {
+    let p = polar();
+    qvar(&p, "y = 2 * (3 + 1)", "y", values![8]);
+    qvar(&p, "y = (1 + 2) * (3 - 1) / 2", "y", values![3.0]);
+    qeval(&p, "2 * (3 + 1) == 8");
+
+    p.load_str("f(x, y) if y = 2 * (x + 1);")?;
+    qvar(&p, "f(3, y)", "y", values![8]);
+    qeval(&p, "f(3, 8)");
+    qnull(&p, "f(3, 9)");
+
+    // Operands bound earlier in the query are dereferenced before evaluation.
+    qvar(&p, "x = 2 and y = x + 1", "y", values![3]);
+    qvar(&p, "a = 1 and b = 2 and z = (a + b) * 2", "z", values![6]);
+    qvar(
+        &p,
+        "a := 1.5 and b := 2 and z := (a + b) * 2",
+        "z",
+        values![7.0],
+    );
+    qruntime!(
+        "x := 1 and x := x + 1",
+        TypeError { msg: s, .. },
+        s == "Can only assign to unbound variables, x is not unbound."
+    );
+
+    // Bad operands are reported as errors rather than panicking.
+    qruntime!("x = 1 mod 0", ArithmeticError { .. });
+    qruntime!("x = 1 rem 0", ArithmeticError { .. });
+    qruntime!(r#"x = "a" and y = x + 1"#, Unsupported { .. });
+    qruntime!("x = [1] and y = x * 2", Unsupported { .. });
+    Ok(())
+}
+
+#[test]
+fn test_arithmetic_with_bound_result() -> TestResult {
+    let p = polar();
+    qeval(&p, "x = 3 and x = 1 + 2");
+    qnull(&p, "x = 4 and x = 1 + 2");
+    qeval(&p, "x = 6 and y = 2 and x = y * 3");
+    qnull(&p, "x = 6.5 and x = 13 / 2 + 1");
+
+    p.load_str("sum(x, y, x + y);")?;
+    qeval(&p, "sum(1, 2, 3)");
+    qnull(&p, "sum(1, 2, 4)");
+    Ok(())
+}
+
 #[test]
 fn test_debug_break_on_error() -> TestResult {
     let p = polar();
@@ -1387,7 +2757,9 @@ fn test_debug_temp_var() -> TestResult {
 
                     001: foo(a, aa) if a < 10 and debug() and aa < a;
                                                   ^
-                    "#
+
+                    a@_a_3 = 5
+                    aa@_aa_4 = 3"#
                 );
                 assert_eq!(s, expected);
                 "var a"
@@ -1583,6 +2955,55 @@ fn test_debug() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_debug_shows_rule_bindings() -> TestResult {
+    let p = polar();
+    p.load_str("f(x, y, z) if y = x + 1 and debug() and z = y;")?;
+
+    let mut messages = vec![];
+    let q = p.new_query("f(1, a, b)", false)?;
+    let results = query_results!(q, no_results, no_externals, |s: &str| {
+        messages.push(s.to_string());
+        "continue".to_string()
+    });
+    assert_eq!(results.len(), 1);
+    assert_eq!(messages.len(), 1);
+    let bindings = messages[0].lines().rev().take(3).collect::<Vec<_>>();
+    assert!(bindings[0].starts_with("z@_z_"));
+    assert!(bindings[0].ends_with(" = b"));
+    assert!(bindings[1].starts_with("y@_y_"));
+    assert!(bindings[1].ends_with(" = 2"));
+    assert!(bindings[2].starts_with("x@_x_"));
+    assert!(bindings[2].ends_with(" = 1"));
+    Ok(())
+}
+
+#[test]
+fn test_debug_breakpoints() -> TestResult {
+    let p = polar();
+    p.load_str(indoc!(
+        r#"a() if debug() and b() and c() and c();
+           b();
+           c();"#
+    ))?;
+
+    let commands = ["break c", "continue", "continue", "clear", "continue"];
+    let mut messages = vec![];
+    let q = p.new_query("a()", false)?;
+    let results = query_results!(q, no_results, no_externals, |s: &str| {
+        messages.push(s.to_string());
+        commands[messages.len() - 1].to_string()
+    });
+    assert_eq!(results.len(), 1);
+    assert_eq!(messages.len(), 5);
+    assert!(messages[0].starts_with("QUERY: debug()"));
+    assert_eq!(messages[1], "Breakpoints: c");
+    assert_eq!(messages[2], "c();");
+    assert_eq!(messages[3], "c();");
+    assert_eq!(messages[4], "No breakpoints set.");
+    Ok(())
+}
+
 #[test]
 fn test_debug_in_inverter() {
     let polar = polar();
@@ -1808,6 +3229,40 @@ fn test_print() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_per_query_log_levels() -> TestResult {
+    let p = polar();
+    p.load_str("f(x) if x = 1; g(x) if f(x);")?;
+    let log = |level: &str| -> PolarResult<Vec<String>> {
+        let mut messages = vec![];
+        let message_handler = |output: &Message| {
+            if matches!(&output.kind, MessageKind::Print) {
+                messages.push(output.msg.clone());
+            }
+        };
+        let mut q = p.new_query("g(x)", false)?;
+        q.set_logging_options(None, Some(level.to_owned()));
+        let _results = query_results!(q, @msgs message_handler);
+        Ok(messages)
+    };
+
+    // Logging is configured per query, so queries can be traced selectively.
+    assert!(log("off")?.is_empty());
+
+    // `info` logs the rules that are applied...
+    let info = log("info")?;
+    assert!(info.iter().any(|msg| msg.contains("RULE: g(x)")));
+    assert!(info.iter().all(|msg| msg.starts_with("[oso][info]")));
+
+    // ...and `trace` also logs every query and unification along the way.
+    let trace = log("trace")?;
+    assert!(trace.iter().any(|msg| msg.contains("RULE: g(x)")));
+    assert!(trace.iter().any(|msg| msg.contains("QUERY: ")));
+    assert!(trace.iter().any(|msg| msg.starts_with("[oso][trace]")));
+    assert!(trace.len() > info.len());
+    Ok(())
+}
+
 #[test]
 fn test_unknown_specializer_suggestions() -> TestResult {
     let p = polar();
@@ -1867,6 +3322,45 @@ fn test_dict_destructuring() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_dict_unification() -> TestResult {
+    let p = polar();
+    qvar(&p, "{a: x} = {a: 1}", "x", values![1]);
+    qvar(&p, "{a: 1, b: x} = {b: 2, a: 1}", "x", values![2]);
+    qvar(&p, "{a: {b: [x]}} = {a: {b: [3]}}", "x", values![3]);
+    qnull(&p, "{a: x} = {a: 1, b: 2}");
+    qnull(&p, "{a: 1} = {b: 1}");
+    qnull(&p, "{a: x, b: 2} = {a: 1, b: 3}");
+
+    // Unification requires the same keys; `matches` only needs the pattern's.
+    qeval(&p, "{a: 1, b: 2} matches {a: 1}");
+    Ok(())
+}
+
+#[test]
+fn test_occurs_check() -> TestResult {
+    let p = polar();
+    let run = |src| query_with(&p, src, |q| q.set_occurs_check(true)).map(|r| r.len());
+
+    assert_eq!(run("x = [x]")?, 0);
+    assert_eq!(run("[x] = x")?, 0);
+    assert_eq!(run("x = [1, [2, x]]")?, 0);
+    assert_eq!(run("x = {a: x}")?, 0);
+    assert_eq!(run("x = [*x]")?, 0);
+    assert_eq!(run("x = y and y = [x]")?, 0);
+    assert_eq!(run("x = [y] and y = [x]")?, 0);
+    assert_eq!(run("x = [y] and y = x")?, 0);
+    assert_eq!(run("x = [y] and x = y")?, 0);
+    assert_eq!(run("x = y and x = y")?, 1);
+    assert_eq!(run("x = [1] and y = x")?, 1);
+    assert_eq!(run("x = [y] and y = [1]")?, 1);
+    assert_eq!(run("x = [1, *y] and y = [2]")?, 1);
+
+    // Without the check, the term is cyclic (see `test_circular_data`).
+    qeval(&p, "x = [x]");
+    Ok(())
+}
+
 #[ignore]
 #[test]
 fn test_dict_destructuring_broken() -> TestResult {
@@ -1889,6 +3383,22 @@ fn test_rest_vars() -> TestResult {
     qvar(&p, "[1,2,3] = [1,2,*rest]", "rest", vec![value!([3])]);
     qvar(&p, "[1,2,3] = [1,2,3,*rest]", "rest", vec![value!([])]);
     qnull(&p, "[1,2,3] = [1,2,3,4,*_rest]");
+    qvar(&p, "[*rest] = []", "rest", vec![value!([])]);
+    qvar(&p, "[first, *rest] = [1,2,3]", "first", values![1]);
+    qvar(&p, "[first, *rest] = [1,2,3]", "rest", vec![value!([2, 3])]);
+
+    // Lists unify element by element, and a failure part way through
+    // leaves none of the earlier elements bound.
+    qvar(&p, "[x, 2] = [1, 2]", "x", values![1]);
+    qnull(&p, "[x, 2] = [1, 3]");
+    qnull(&p, "[x, y] = [1, 2, 3]");
+    qvar(&p, "([x, 2] = [1, 3] or true) and x = 5", "x", values![5]);
+    qvar(
+        &p,
+        "([x, *_] = [1, 2] and false or true) and x = 5",
+        "x",
+        values![5],
+    );
 
     p.load_str(
         r#"member(x, [x, *_rest]);
@@ -1920,6 +3430,14 @@ fn test_rest_vars() -> TestResult {
     let a = &var(&p, "[*c] in [*a] and [*b] in [*d] and b = 1", "a")[0];
     // check that a isn't bound to [b]
     assert!(!matches!(a, Value::List(b) if matches!(b[0].value(), Value::Number(_))));
+
+    // A rest variable bound to something other than a list can't be spliced
+    // into a list: unifying against it fails, and iterating it is an error.
+    qnull(&p, "x = 1 and [1, *x] = [1, 2]");
+    qnull(&p, "x = 1 and [1, 2] = [1, *x]");
+    qnull(&p, "x = 1 and [*x] = []");
+    qnull(&p, r#"x = "ab" and [*x] = ["a", "b"]"#);
+    qruntime!("x = 1 and 1 in [*x]", TypeError { .. });
     Ok(())
 }
 
@@ -1972,6 +3490,23 @@ fn test_data_filtering_pattern_specializers() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_in_registered_constant() -> TestResult {
+    let p = polar();
+    p.register_constant(sym!("ACTIONS"), term!(["read", "write"]))?;
+    p.load_str("allow(_, action, _) if action in ACTIONS;")?;
+    qeval(&p, r#"allow("alice", "read", "doc")"#);
+    qeval(&p, r#"allow("alice", "write", "doc")"#);
+    qnull(&p, r#"allow("alice", "delete", "doc")"#);
+    qvar(
+        &p,
+        r#"allow("alice", a, "doc")"#,
+        "a",
+        values!["read", "write"],
+    );
+    Ok(())
+}
+
 #[test]
 fn test_in_op() -> TestResult {
     let p = polar();
@@ -1983,6 +3518,21 @@ fn test_in_op() -> TestResult {
     qnull(&p, "4 in [1,2,3]");
     qeval(&p, "4 in [1,2,3] or 1 in [1,2,3]");
 
+    // One result per matching element.
+    assert_eq!(
+        query_results!(p.new_query("2 in [1, 2, 3]", false)?).len(),
+        1
+    );
+    assert_eq!(query_results!(p.new_query("2 in [2, 2]", false)?).len(), 2);
+
+    // Membership in a non-iterable value is a type error.
+    qruntime!(
+        "1 in 5",
+        TypeError { msg: s, .. },
+        s.starts_with("can only use `in` on an iterable value")
+    );
+    qruntime!("x in true", TypeError { .. });
+
     // Make sure we scan the whole list.
     let q = p.new_query("1 in [1, 2, x, 1]", false)?;
     let results = query_results!(q);
@@ -2025,6 +3575,26 @@ fn test_in_op() -> TestResult {
         &p,
         "a = [1, *b] and b = [2, *c] and c = [3] and 1 in a and 2 in a and 3 in a",
     );
+
+    // Nested lists yield their elements whole.
+    qvar(
+        &p,
+        "x in [[1, 2], [3]]",
+        "x",
+        vec![value!([1, 2]), value!([3])],
+    );
+    qvar(&p, "l in [[1, 2], [3]] and x in l", "x", values![1, 2, 3]);
+    qeval(&p, "[3] in [[1, 2], [3]]");
+    qnull(&p, "3 in [[1, 2], [3]]");
+
+    // Lists stored in dictionaries.
+    qvar(&p, "d = {a: [1, 2]} and x in d.a", "x", values![1, 2]);
+    qvar(
+        &p,
+        "d = {a: [1], b: [2, 3]} and [_, l] in d and x in l",
+        "x",
+        values![1, 2, 3],
+    );
     Ok(())
 }
 
@@ -2051,6 +3621,26 @@ fn test_matches() {
     qeval(&p, "x matches Integer and x = 1");
 }
 
+#[test]
+fn test_matches_union() {
+    // `x matches (A or B)` has one result for each pattern that matches.
+    let p = polar();
+    let union = "({a: 1} or {b: 2})";
+    qeval(&p, &format!("{{a: 1}} matches {}", union));
+    qeval(&p, &format!("{{b: 2, c: 3}} matches {}", union));
+    qnull(&p, &format!("{{c: 3}} matches {}", union));
+    let q = p
+        .new_query(&format!("{{a: 1, b: 2}} matches {}", union), false)
+        .unwrap();
+    assert_eq!(query_results!(q).len(), 2);
+
+    // Cut to stop at the first pattern that matches.
+    p.load_str(&format!("any(x) if x matches {} and cut;", union))
+        .unwrap();
+    let q = p.new_query("any({a: 1, b: 2})", false).unwrap();
+    assert_eq!(query_results!(q).len(), 1);
+}
+
 #[test]
 fn test_keyword_call() {
     qparse!("cut(a) if a;", ReservedWord { .. });
@@ -2148,6 +3738,53 @@ fn test_cut() -> TestResult {
     qvar(&p, "f(x)", "x", values![1]);
     qeval(&p, "f(1)");
     qeval(&p, "f(2)");
+
+    p.clear_rules();
+
+    // A cut in one rule discards the rules that would have been tried after
+    // it, but only once the cut is reached.
+    p.load_str(
+        r#"g(x) if x in [1, 2] and cut;
+           g(3);
+           size(n, s) if n > 5 and cut and s = "big";
+           size(_, "small");"#,
+    )?;
+    qvar(&p, "g(x)", "x", values![1]);
+    qvar(&p, "size(9, s)", "s", values!["big"]);
+    qvar(&p, "size(1, s)", "s", values!["small"]);
+    Ok(())
+}
+
+#[test]
+fn test_cut_in_disjunction() -> TestResult {
+    let p = polar();
+    p.register_constant(sym!("Foo"), term!(true))?;
+    p.load_str(
+        r#"f(x) if (x = 1 and cut) or new Foo().tried() = x;
+           f(3);"#,
+    )?;
+
+    // Cutting in the first disjunct prunes the other disjunct
+    // and the remaining `f` rules.
+    let mut calls = 0;
+    let q = p.new_query("f(x)", false)?;
+    let results = query_results!(q, |_, _, _, _, _| {
+        calls += 1;
+        Some(term!(2))
+    });
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0[&sym!("x")], value!(1));
+    assert_eq!(calls, 0);
+
+    // If the first disjunct fails before the cut, the others are tried.
+    let mut calls = 0;
+    let q = p.new_query("f(2)", false)?;
+    let results = query_results!(q, |_, _, _, _, _| {
+        calls += 1;
+        Some(term!(2))
+    });
+    assert_eq!(results.len(), 1);
+    assert_eq!(calls, 1);
     Ok(())
 }
 
@@ -2161,6 +3798,38 @@ fn test_forall_with_dots_on_rhs() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_assert() -> TestResult {
+    let p = polar();
+    qeval(&p, "assert(1 = 1)");
+    qvar(&p, "assert(x = 1 or x = 2)", "x", values![1, 2]);
+    qnull(&p, "assert(x = 1) and x = 2");
+    qruntime!("assert(1 = 2)", AssertionFailed { .. });
+
+    p.load_str("positive(x) if assert(x > 0);")?;
+    qeval(&p, "positive(1)");
+    let e = _qruntime(&p, "positive(-1)");
+    assert!(
+        matches!(e.0, ErrorKind::Runtime(AssertionFailed { .. })),
+        "{}",
+        e
+    );
+    assert!(e.to_string().contains("at line 1, column 23"), "{}", e);
+
+    // Conditions on rule parameters and aliased variables can bind them.
+    p.clear_rules();
+    p.load_str("one(x) if assert(x = 1);")?;
+    qvar(&p, "one(y)", "y", values![1]);
+    qeval(&p, "one(1)");
+    qruntime!(&p, "one(2)", AssertionFailed { .. });
+    qvars(&p, "x = y and assert(x = 1)", &["x", "y"], values![[1, 1]]);
+
+    // A condition on a partial variable only adds a constraint.
+    let results = query_results!(p.new_query("x > 0 and assert(x > 1)", false)?);
+    assert_eq!(results.len(), 1);
+    Ok(())
+}
+
 #[test]
 fn test_forall() -> TestResult {
     let p = polar();
@@ -2234,6 +3903,40 @@ fn test_boolean_expression() {
     qnull(&p, "a = false and a");
 }
 
+#[test]
+fn test_boolean_rule_bodies() -> TestResult {
+    let p = polar();
+    p.load_str(
+        r#"f() if false;
+           g() if true;
+           h(x) if x = 1 and true or x = 2 and false or x = 3;
+           k(x) if (false or x = 1) and (x = 1 or false);"#,
+    )?;
+    qnull(&p, "f()");
+    assert_eq!(var(&p, "g() and x = 1", "x").len(), 1);
+    qvar(&p, "h(x)", "x", values![1, 3]);
+    qvar(&p, "k(x)", "x", values![1]);
+    qnull(&p, "f() or false");
+    qeval(&p, "f() or g()");
+    Ok(())
+}
+
+#[test]
+fn test_unify_booleans_in_compounds() {
+    let p = polar();
+    qvar(&p, "[true, x] = [true, false]", "x", values![false]);
+    qnull(&p, "[true, x] = [false, false]");
+    qvar(
+        &p,
+        "{a: true, b: x} = {a: true, b: false}",
+        "x",
+        values![false],
+    );
+    qnull(&p, "{a: true, b: x} = {a: false, b: false}");
+    qnull(&p, "[true] = [1]");
+    qnull(&p, "{a: false} = {a: 0}");
+}
+
 #[test]
 fn test_float_parsing() {
     let p = polar();
@@ -2314,6 +4017,43 @@ fn test_duplicated_rule() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_dedup_rules() -> TestResult {
+    let p = polar();
+    p.set_dedup_rules(true);
+    p.load_str(
+        r#"f(1);
+           f(1);
+           f(2);
+           g(x) if f(x) and x > 1;
+           g(x) if f(x) and x > 1;"#,
+    )?;
+    qvar(&p, "f(x)", "x", values![1, 2]);
+    qvar(&p, "g(x)", "x", values![2]);
+
+    // Facts synced in later are deduplicated against the loaded rules.
+    p.load_facts("f", vec![vec![term!(2)], vec![term!(3)]])?;
+    qvar(&p, "f(x)", "x", values![1, 2, 3]);
+    Ok(())
+}
+
+#[test]
+fn test_mixed_specialized_and_unspecialized_rules() -> TestResult {
+    let p = polar();
+    p.load_str(
+        r#"f(_: {kind: "admin"}, "admin");
+           f(_, "any");
+           f(_: {kind: "user"}, "user");"#,
+    )?;
+    // Specialized rules are more specific, so they come first.
+    qvar(&p, r#"f({kind: "admin"}, r)"#, "r", values!["admin", "any"]);
+    qvar(&p, r#"f({kind: "user"}, r)"#, "r", values!["user", "any"]);
+    // An unspecialized parameter matches anything.
+    qvar(&p, r#"f({kind: "guest"}, r)"#, "r", values!["any"]);
+    qvar(&p, "f(1, r)", "r", values!["any"]);
+    Ok(())
+}
+
 #[test]
 fn test_numeric_applicability() -> TestResult {
     let p = polar();
@@ -2454,6 +4194,9 @@ fn test_list_matches() {
     qnull(&p, "[2, x] matches [1, 2]");
     qvar(&p, "[1, 2, x] matches [1, 2, 3]", "x", values![3]);
     qnull(&p, "[1, 2, 3] matches [1, x]");
+    qvars(&p, "[a, b] matches [1, 2]", &["a", "b"], values![[1, 2]]);
+    qnull(&p, "[a] matches [1, 2]");
+    qnull(&p, "[a, b, c] matches [1, 2]");
 
     qvar(&p, "[] matches [*ys]", "ys", vec![value!([])]);
     qvar(&p, "[*xs] matches []", "xs", vec![value!([])]);
@@ -2506,6 +4249,13 @@ fn test_builtin_iterables() {
 
     qeval(&p, r#""b" in "abc""#);
     qnull(&p, r#""d" in "abc""#);
+    qeval(&p, r#""bc" in "abc""#);
+    qeval(&p, r#""" in "abc""#);
+    qnull(&p, r#""ac" in "abc""#);
+    qnull(&p, r#"1 in "abc""#);
+    // A substring matches once, however often it occurs.
+    assert_eq!(var(&p, r#"x = "a" and x in "abacus""#, "x").len(), 1);
+    qvar(&p, r#"x in "aba""#, "x", values!["a", "b", "a"]);
     qeval(&p, r#"forall(x in "abc", x in "abacus")"#);
     qnull(&p, r#"forall(x in "abcd", x in "abacus")"#);
 }